@@ -3,7 +3,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 fn commit_hash() -> Option<String> {
     Command::new("git")
-        .args(&["rev-parse", "--short", "HEAD"])
+        .args(["rev-parse", "--short", "HEAD"])
         .output()
         .ok()
         .and_then(|output| String::from_utf8(output.stdout).ok())
@@ -12,7 +12,7 @@ fn commit_hash() -> Option<String> {
 
 fn build_ts() -> Option<String> {
     Command::new("date")
-        .args(&["+%Y-%m-%d %H:%M:%S %Z"])
+        .args(["+%Y-%m-%d %H:%M:%S %Z"])
         .output()
         .ok()
         .and_then(|output| String::from_utf8(output.stdout).ok())
@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use stat_client::procfs::get_sys_traffic_from_reader;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = get_sys_traffic_from_reader(data);
+});
@@ -0,0 +1,212 @@
+// node local alerting, independent of the central server
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use stat_common::server_status::StatRequest;
+
+use crate::Args;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertOp {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+}
+
+impl AlertOp {
+    fn eval(self, lhs: f64, rhs: f64) -> bool {
+        match self {
+            AlertOp::Gt => lhs > rhs,
+            AlertOp::Ge => lhs >= rhs,
+            AlertOp::Lt => lhs < rhs,
+            AlertOp::Le => lhs <= rhs,
+            AlertOp::Eq => (lhs - rhs).abs() < f64::EPSILON,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertRule {
+    pub raw: String,
+    pub metric: String,
+    pub op: AlertOp,
+    pub value: f64,
+    // secs the condition must hold
+    pub duration: u64,
+}
+
+// metric:op:value:duration, eg: cpu:>:95:60
+pub fn parse_alert_rule(s: &str) -> Result<AlertRule, String> {
+    let vec = s.split(':').collect::<Vec<_>>();
+    if vec.len() != 4 {
+        return Err(format!("expect metric:op:value:duration, got `{}`", s));
+    }
+    let op = match vec[1] {
+        ">" | "gt" => AlertOp::Gt,
+        ">=" | "ge" => AlertOp::Ge,
+        "<" | "lt" => AlertOp::Lt,
+        "<=" | "le" => AlertOp::Le,
+        "==" | "eq" => AlertOp::Eq,
+        op => return Err(format!("invalid op `{}`", op)),
+    };
+    Ok(AlertRule {
+        raw: s.to_string(),
+        metric: vec[0].to_string(),
+        op,
+        value: vec[2]
+            .parse::<f64>()
+            .map_err(|err| format!("invalid value `{}`, {}", vec[2], err))?,
+        duration: vec[3]
+            .parse::<u64>()
+            .map_err(|err| format!("invalid duration `{}`, {}", vec[3], err))?,
+    })
+}
+
+// percent helpers, otherwise any numeric StatRequest field
+fn metric_value(stat: &StatRequest, json: &serde_json::Value, metric: &str) -> Option<f64> {
+    let pct = |used: u64, total: u64| {
+        if total == 0 {
+            None
+        } else {
+            Some(100.0 * used as f64 / total as f64)
+        }
+    };
+    match metric {
+        "mem_pct" => pct(stat.memory_used, stat.memory_total),
+        "swap_pct" => pct(stat.swap_used, stat.swap_total),
+        "hdd_pct" => pct(stat.hdd_used, stat.hdd_total),
+        _ => json.get(metric)?.as_f64(),
+    }
+}
+
+#[derive(Debug, Default)]
+struct RuleState {
+    breach_since: Option<u64>,
+    last_fired: Option<u64>,
+}
+
+static G_ALERT_STATE: Lazy<Mutex<HashMap<usize, RuleState>>> = Lazy::new(Default::default);
+
+pub fn evaluate(args: &Args, stat: &StatRequest) {
+    if args.alert_rule.is_empty() {
+        return;
+    }
+    let json = match serde_json::to_value(stat) {
+        Ok(json) => json,
+        Err(_) => return,
+    };
+    let now = stat.latest_ts;
+
+    let mut fired = Vec::new();
+    if let Ok(mut states) = G_ALERT_STATE.lock() {
+        for (idx, rule) in args.alert_rule.iter().enumerate() {
+            let state = states.entry(idx).or_default();
+            let value = match metric_value(stat, &json, &rule.metric) {
+                Some(value) => value,
+                None => {
+                    state.breach_since = None;
+                    continue;
+                }
+            };
+            if !rule.op.eval(value, rule.value) {
+                state.breach_since = None;
+                continue;
+            }
+
+            let since = *state.breach_since.get_or_insert(now);
+            let cooling = state
+                .last_fired
+                .map_or(false, |ts| now < ts + args.alert_cooldown);
+            if now.saturating_sub(since) >= rule.duration && !cooling {
+                state.last_fired = Some(now);
+                fired.push(serde_json::json!({
+                    "name": stat.name,
+                    "rule": rule.raw,
+                    "metric": rule.metric,
+                    "value": value,
+                    "threshold": rule.value,
+                    "ts": now,
+                }));
+            }
+        }
+    }
+
+    for alert in fired {
+        warn!("alert => {}", alert);
+        if let Some(url) = args.alert_webhook.as_ref() {
+            let url = url.to_string();
+            tokio::spawn(async move {
+                let result = reqwest::Client::new()
+                    .post(&url)
+                    .timeout(Duration::from_secs(5))
+                    .json(&alert)
+                    .send()
+                    .await;
+                if let Err(err) = result {
+                    error!("alert webhook error => {:?}", err);
+                }
+            });
+        }
+    }
+}
+
+// --syslog-alert, fixed critical thresholds, logged once on crossing and once on recovery
+const SYSLOG_MEM_PCT: f64 = 95.0;
+const SYSLOG_HDD_PCT: f64 = 95.0;
+const SYSLOG_LOAD_PER_CPU: f64 = 2.0;
+
+static G_SYSLOG_STATE: Lazy<Mutex<HashMap<&'static str, bool>>> = Lazy::new(Default::default);
+
+// (check, value, threshold)
+fn syslog_checks(stat: &StatRequest, cpus: usize) -> Vec<(&'static str, f64, f64)> {
+    let mut checks = Vec::new();
+    if stat.memory_total > 0 {
+        let mem_pct = 100.0 * stat.memory_used as f64 / stat.memory_total as f64;
+        checks.push(("mem_pct", mem_pct, SYSLOG_MEM_PCT));
+    }
+    if stat.hdd_total > 0 {
+        let hdd_pct = 100.0 * stat.hdd_used as f64 / stat.hdd_total as f64;
+        checks.push(("hdd_pct", hdd_pct, SYSLOG_HDD_PCT));
+    }
+    checks.push(("load_1", stat.load_1, cpus as f64 * SYSLOG_LOAD_PER_CPU));
+    checks
+}
+
+pub fn evaluate_syslog(args: &Args, stat: &StatRequest) {
+    if !args.syslog_alert {
+        return;
+    }
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut msgs = Vec::new();
+    if let Ok(mut states) = G_SYSLOG_STATE.lock() {
+        for (check, value, threshold) in syslog_checks(stat, cpus) {
+            let crossed = value > threshold;
+            let was = states.insert(check, crossed).unwrap_or(false);
+            if crossed && !was {
+                msgs.push((
+                    crate::syslog::SEV_CRIT,
+                    format!("{} {:.1} > {:.1}", check, value, threshold),
+                ));
+            } else if !crossed && was {
+                msgs.push((
+                    crate::syslog::SEV_NOTICE,
+                    format!("{} {:.1} back under {:.1}", check, value, threshold),
+                ));
+            }
+        }
+    }
+
+    for (severity, msg) in msgs {
+        let msg = format!("{}: {}", stat.name, msg);
+        warn!("syslog alert => {}", msg);
+        if let Err(err) =
+            crate::syslog::send_syslog(args.syslog_facility, severity, env!("CARGO_BIN_NAME"), &msg)
+        {
+            error!("syslog alert error => {:?}", err);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// cheap monotonic clock for the collector threads,
+// rdtsc on x86_64 skips the clock_gettime call on each sample
+use once_cell::sync::Lazy;
+use std::time::Instant;
+
+// the single monotonic clock read at startup, everything else is relative to it
+static START: Lazy<Instant> = Lazy::new(Instant::now);
+
+// some of the intrinsics are safe fns on newer toolchains
+#[cfg(target_arch = "x86_64")]
+#[allow(unused_unsafe)]
+mod tsc {
+    use once_cell::sync::Lazy;
+    use std::arch::x86_64::{__cpuid, _rdtsc};
+    use std::thread;
+    use std::time::Duration;
+
+    // (tsc at start, ns per tick), None without an invariant tsc
+    pub static CALIB: Lazy<Option<(u64, f64)>> = Lazy::new(|| {
+        if !invariant_tsc() {
+            return None;
+        }
+        let start = *super::START;
+        let tsc0 = unsafe { _rdtsc() };
+        let ns0 = start.elapsed().as_nanos() as u64;
+        thread::sleep(Duration::from_millis(10));
+        let tsc1 = unsafe { _rdtsc() };
+        let ns1 = start.elapsed().as_nanos() as u64;
+
+        let ticks = tsc1.wrapping_sub(tsc0);
+        if ticks == 0 {
+            return None;
+        }
+        let ns_per_tick = (ns1 - ns0) as f64 / ticks as f64;
+        // align tick 0 with START
+        let base = tsc0.wrapping_sub((ns0 as f64 / ns_per_tick) as u64);
+        info!("tsc calibrated, {:.3} ns/tick", ns_per_tick);
+        Some((base, ns_per_tick))
+    });
+
+    // cpuid 0x80000007 edx bit 8, constant rate across p/c states
+    fn invariant_tsc() -> bool {
+        let max_ext = unsafe { __cpuid(0x8000_0000) }.eax;
+        max_ext >= 0x8000_0007 && unsafe { __cpuid(0x8000_0007) }.edx & (1 << 8) != 0
+    }
+
+    pub fn nanos() -> Option<u64> {
+        CALIB.map(|(base, ns_per_tick)| {
+            (unsafe { _rdtsc() }.wrapping_sub(base) as f64 * ns_per_tick) as u64
+        })
+    }
+}
+
+// nanos since startup
+pub fn fast_monotonic_nanos() -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    if let Some(ns) = tsc::nanos() {
+        return ns;
+    }
+    START.elapsed().as_nanos() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn monotonic_nanos_tracks_instant() {
+        // the first call calibrates
+        let mut prev = fast_monotonic_nanos();
+        for _ in 0..1000 {
+            let now = fast_monotonic_nanos();
+            assert!(now >= prev);
+            prev = now;
+        }
+
+        let t0 = Instant::now();
+        let n0 = fast_monotonic_nanos();
+        thread::sleep(Duration::from_millis(50));
+        let n1 = fast_monotonic_nanos();
+        let elapsed = t0.elapsed().as_nanos() as i64;
+        let drift = (n1 - n0) as i64 - elapsed;
+        assert!(
+            drift.abs() < 5_000_000,
+            "drift {}ns over {}ns",
+            drift,
+            elapsed
+        );
+    }
+}
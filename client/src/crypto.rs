@@ -0,0 +1,54 @@
+// app layer report encryption, ChaCha20-Poly1305
+//
+// wire format, sent with `X-Encrypt: chacha20poly1305`:
+//   nonce (12 bytes, random per message) || ciphertext || tag (16 bytes)
+// the plaintext is the usual body, protobuf or json StatRequest as Content-Type says,
+// the key is 32 bytes given as 64 hex chars or standard base64
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+pub const NONCE_LEN: usize = 12;
+pub const ENCRYPT_HEADER: &str = "X-Encrypt";
+pub const ENCRYPT_ALGO: &str = "chacha20poly1305";
+
+pub fn parse_key(s: &str) -> Result<[u8; 32], String> {
+    let s = s.trim();
+    let bytes = if s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit()) {
+        (0..64)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect::<Vec<_>>()
+    } else {
+        STANDARD
+            .decode(s)
+            .map_err(|err| format!("invalid key, expect hex or base64, {}", err))?
+    };
+    bytes
+        .try_into()
+        .map_err(|v: Vec<u8>| format!("invalid key length {}, expect 32 bytes", v.len()))
+}
+
+pub fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|err| format!("encrypt error, {}", err))?;
+
+    let mut buf = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    buf.extend_from_slice(&nonce);
+    buf.extend_from_slice(&ciphertext);
+    Ok(buf)
+}
+
+pub fn decrypt(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < NONCE_LEN {
+        return Err("payload too short".to_string());
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|err| format!("decrypt error, {}", err))
+}
@@ -1,25 +1,29 @@
 // #![allow(unused)]
 use std::net::ToSocketAddrs;
+use std::sync::atomic::Ordering;
 use std::thread;
-use std::time::Duration;
-use tonic::transport::Channel;
+use std::time::{Duration, Instant};
+use tonic::transport::{Channel, Uri};
 use tonic::{metadata::MetadataValue, Request};
+use tower::service_fn;
 use tower::timeout::Timeout;
 
 use stat_common::server_status::server_status_client::ServerStatusClient;
 use stat_common::server_status::StatRequest;
 
-use crate::sample_all;
+use stat_client::proxy;
+
+use crate::health;
+use crate::report_interval;
 use crate::Args;
+use crate::ReportSchedule;
+use crate::G_REPORT_RTT_MS;
 use crate::INTERVAL_MS;
 
 // TODO TLS
 
 pub async fn report(args: &Args, stat_base: &mut StatRequest) -> anyhow::Result<()> {
-    if !vec![stat_base.online4, stat_base.online6]
-        .iter()
-        .any(|&x| x)
-    {
+    if ![stat_base.online4, stat_base.online6].iter().any(|&x| x) {
         eprintln!("try get target network...");
         let addr = args.addr.replace("grpc://", "");
         let sock_addr = addr.to_socket_addrs()?.next().unwrap();
@@ -33,27 +37,62 @@ pub async fn report(args: &Args, stat_base: &mut StatRequest) -> anyhow::Result<
         );
     }
 
-    let token = MetadataValue::try_from(format!("{}@_@{}", args.user, args.pass))?;
+    let token = MetadataValue::try_from(format!("{}@_@{}", args.user, args.pass.0))?;
 
-    let channel = Channel::from_shared(args.addr.to_string())?
-        .connect()
-        .await?;
+    // direct unless HTTP_PROXY/HTTPS_PROXY applies, then an http CONNECT tunnel
+    let endpoint = Channel::from_shared(args.addr.to_string())?;
+    let host_port = args.addr.replace("grpc://", "");
+    let host = host_port
+        .rsplit_once(':')
+        .map_or(host_port.as_str(), |(host, _)| host);
+    let channel = match proxy::env_proxy_for(host, true) {
+        Some(proxy_url) => {
+            let target = host_port
+                .to_socket_addrs()?
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("resolve {} failed", host_port))?;
+            // the url may carry credentials, log the host only
+            info!(
+                "grpc via proxy {} => {}",
+                proxy_url.host_str().unwrap_or_default(),
+                target
+            );
+            endpoint
+                .connect_with_connector(service_fn(move |_: Uri| {
+                    let proxy_url = proxy_url.clone();
+                    async move { proxy::connect_via_proxy(&proxy_url, &target).await }
+                }))
+                .await?
+        }
+        None => endpoint.connect().await?,
+    };
     let timeout_channel = Timeout::new(channel, Duration::from_millis(3000));
 
+    // tonic interceptors return Result<_, Status>
+    #[allow(unknown_lints, clippy::result_large_err)]
     let grpc_client =
         ServerStatusClient::with_interceptor(timeout_channel, move |mut req: Request<()>| {
             req.metadata_mut().insert("authorization", token.clone());
             Ok(req)
         });
 
+    let mut schedule = ReportSchedule::default();
     loop {
-        let stat_rt = sample_all(args, stat_base);
+        // a paused loop still counts as alive
+        health::mark_cycle();
+        if args.skip_report() {
+            thread::sleep(Duration::from_millis(INTERVAL_MS));
+            continue;
+        }
+        let stat_rt = schedule.next(args, stat_base);
         let mut client = grpc_client.clone();
         tokio::spawn(async move {
             let request = tonic::Request::new(stat_rt);
 
+            let start = Instant::now();
             match client.report(request).await {
                 Ok(resp) => {
+                    G_REPORT_RTT_MS.store(start.elapsed().as_millis() as u64, Ordering::Relaxed);
                     info!("grpc report resp => {:?}", resp);
                 }
                 Err(status) => {
@@ -62,6 +101,6 @@ pub async fn report(args: &Args, stat_base: &mut StatRequest) -> anyhow::Result<
             }
         });
 
-        thread::sleep(Duration::from_millis(INTERVAL_MS));
+        thread::sleep(report_interval(args));
     }
 }
@@ -0,0 +1,236 @@
+// statvfs fields are c_ulong / fsblkcnt_t, u32 on some 32-bit targets
+#![allow(clippy::unnecessary_cast)]
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use stat_client::procfs;
+use stat_client::procfs::{MountEntry, ProcFs};
+use stat_client::units::MIB;
+
+pub static MOUNT_POINT_IGNORE_PREFIXES: &[&str] = &["/proc", "/sys", "/dev", "/run"];
+pub static FS_TYPE_IGNORE_VEC: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smbfs",
+    "smb3",
+    "tmpfs",
+    "devtmpfs",
+    "proc",
+    "sysfs",
+    "cgroup",
+    "cgroup2",
+    "overlay",
+    "squashfs",
+    "autofs",
+    "fuse.sshfs",
+];
+
+static DF_CMD:&str = "df -Tlm --total -t ext4 -t ext3 -t ext2 -t reiserfs -t jfs -t ntfs -t fat32 -t btrfs -t fuseblk -t zfs -t simfs -t xfs";
+// bytes, df -m rounds to MiB
+// df exits 1 when some filesystems fail, eg: a stale nfs mount, the total row may still be there
+pub fn try_get_hdd() -> io::Result<(u64, u64)> {
+    try_get_hdd_mounts(&[])
+}
+
+// df restricted to the given mount points, every local fs when empty
+pub fn try_get_hdd_mounts(mounts: &[String]) -> io::Result<(u64, u64)> {
+    let mut args = DF_CMD.split_whitespace();
+    let output = Command::new(args.next().unwrap_or("df"))
+        .args(args)
+        .args(mounts)
+        .output()
+        .map_err(|err| {
+            error!("failed to execute df => {:?}", err);
+            err
+        })?;
+    if !output.status.success() {
+        warn!(
+            "df exit {:?} => {}",
+            output.status.code(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let total = procfs::get_df_total_from_reader(&output.stdout[..]);
+    debug!("df => {:?}", total);
+    total
+        .map(|(total, used)| (total * MIB, used * MIB))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "df without total row"))
+}
+
+pub fn get_hdd() -> (u64, u64) {
+    try_get_hdd().unwrap_or_default()
+}
+
+fn is_ignored_mount(m: &MountEntry) -> bool {
+    let under = |prefix: &str| {
+        m.mount_point == prefix || m.mount_point.starts_with(&format!("{}/", prefix))
+    };
+    MOUNT_POINT_IGNORE_PREFIXES.iter().any(|p| under(p))
+        || FS_TYPE_IGNORE_VEC.iter().any(|t| m.fs_type.eq(*t))
+}
+
+// mounts get_hdd_statfs considers, one entry per device
+pub fn get_hdd_mounts<P: ProcFs>(fs: &P) -> Vec<MountEntry> {
+    let mut seen = HashSet::new();
+    fs.read_mounts()
+        .map(|contents| procfs::get_mounts_from_reader(contents.as_bytes()))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|m| !is_ignored_mount(m))
+        .filter(|m| m.device.starts_with('/') || m.fs_type.eq("zfs"))
+        .filter(|m| seen.insert(m.device.clone()))
+        .collect()
+}
+
+// mount points get_hdd considers that are mounted `ro`
+pub fn get_readonly_mounts<P: ProcFs>(fs: &P) -> Vec<String> {
+    get_hdd_mounts(fs)
+        .into_iter()
+        .filter(|m| m.options.iter().any(|o| o.eq("ro")))
+        .map(|m| m.mount_point)
+        .collect()
+}
+
+// bytes, same totals as df, no fork
+#[cfg(unix)]
+pub fn get_hdd_statfs_from<P: ProcFs>(fs: &P) -> (u64, u64) {
+    use nix::sys::statvfs::statvfs;
+
+    let (mut hdd_total, mut hdd_used) = (0_u64, 0_u64);
+    for m in get_hdd_mounts(fs) {
+        match statvfs(m.mount_point.as_str()) {
+            Ok(st) => {
+                let frsize = st.fragment_size() as u64;
+                let total = st.blocks() as u64 * frsize;
+                let free = st.blocks_free() as u64 * frsize;
+                hdd_total += total;
+                hdd_used += total.saturating_sub(free);
+            }
+            Err(err) => {
+                debug!("statvfs {} => {:?}", m.mount_point, err);
+            }
+        }
+    }
+
+    (hdd_total, hdd_used)
+}
+
+#[cfg(unix)]
+pub fn get_hdd_statfs() -> (u64, u64) {
+    get_hdd_statfs_from(&procfs::RealProcFs)
+}
+
+// bytes, a single statvfs on / for `--hdd-mount /`, no df fork, no /proc/mounts scan
+#[cfg(unix)]
+pub fn try_get_hdd_root() -> io::Result<(u64, u64)> {
+    use nix::sys::statvfs::statvfs;
+
+    let st = statvfs("/").map_err(|err| io::Error::from_raw_os_error(err as i32))?;
+    let frsize = st.fragment_size() as u64;
+    let total = st.blocks() as u64 * frsize;
+    let used = total.saturating_sub(st.blocks_free() as u64 * frsize);
+    Ok((total, used))
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct PartitionUsage {
+    pub mount_point: String,
+    pub device: String,
+    pub total_mb: u64,
+    pub used_mb: u64,
+    pub use_pct: f64,
+}
+
+// given mount points, or every hdd mount when empty
+#[cfg(unix)]
+pub fn get_partition_usage(include_mounts: &[String]) -> Vec<PartitionUsage> {
+    use nix::sys::statvfs::statvfs;
+
+    let mounts = if include_mounts.is_empty() {
+        get_hdd_mounts(&procfs::RealProcFs)
+    } else {
+        procfs::RealProcFs
+            .read_mounts()
+            .map(|contents| procfs::get_mounts_from_reader(contents.as_bytes()))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|m| include_mounts.contains(&m.mount_point))
+            .collect()
+    };
+
+    mounts
+        .into_iter()
+        .filter_map(|m| {
+            let st = statvfs(m.mount_point.as_str())
+                .map_err(|err| debug!("statvfs {} => {:?}", m.mount_point, err))
+                .ok()?;
+            let frsize = st.fragment_size() as u64;
+            let total = st.blocks() as u64 * frsize;
+            let used = total.saturating_sub(st.blocks_free() as u64 * frsize);
+            let avail = st.blocks_available() as u64 * frsize;
+            // same as df Use%, reserved blocks excluded
+            let use_pct = if used + avail == 0 {
+                0.0
+            } else {
+                100.0 * used as f64 / (used + avail) as f64
+            };
+            Some(PartitionUsage {
+                mount_point: m.mount_point,
+                device: m.device,
+                total_mb: total / 1024 / 1024,
+                used_mb: used / 1024 / 1024,
+                use_pct,
+            })
+        })
+        .collect()
+}
+
+const DISK_PROBE_INTERVAL_SECS: u64 = 10;
+const DISK_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub static G_DISK_PROBE_LATENCY_MS: AtomicU64 = AtomicU64::new(0);
+
+// write + fsync + remove a 4KiB zero file under path, Duration::MAX on io error
+pub fn get_disk_write_latency(path: &Path) -> Duration {
+    let file = path.join(format!(".stat_client_probe_{}", std::process::id()));
+    let start = Instant::now();
+    let result = fs::File::create(&file).and_then(|mut f| {
+        f.write_all(&[0_u8; 4096])?;
+        f.sync_all()
+    });
+    let elapsed = start.elapsed();
+    let _ = fs::remove_file(&file);
+    match result {
+        Ok(_) => elapsed,
+        Err(err) => {
+            error!("disk probe {:?} => {:?}", file, err);
+            Duration::MAX
+        }
+    }
+}
+
+// off the report loop, a hung disk must not stall the reports
+pub fn start_disk_probe_t(path: PathBuf) {
+    thread::spawn(move || loop {
+        let latency = get_disk_write_latency(&path);
+        let latency_ms = if latency > DISK_PROBE_TIMEOUT {
+            warn!("disk probe {:?} took {:?}", path, latency);
+            u64::MAX
+        } else {
+            latency.as_millis() as u64
+        };
+        G_DISK_PROBE_LATENCY_MS.store(latency_ms, Ordering::Relaxed);
+
+        thread::sleep(Duration::from_secs(DISK_PROBE_INTERVAL_SECS));
+    });
+}
@@ -0,0 +1,65 @@
+// GET /healthz for container healthchecks, 200 while report cycles keep running,
+// 503 once the last one is older than HEALTH_MAX_AGE_CYCLES intervals
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::INTERVAL_MS;
+
+const HEALTH_MAX_AGE_CYCLES: u64 = 3;
+const HEALTH_READ_TIMEOUT_MS: u64 = 1000;
+
+// unix ms of the last reporter loop iteration, 0 before the first one
+pub static G_LAST_CYCLE_MS: AtomicU64 = AtomicU64::new(0);
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+pub fn mark_cycle() {
+    G_LAST_CYCLE_MS.store(now_ms(), Ordering::Relaxed);
+}
+
+// warmup and the network probe come before the first cycle, count startup as healthy
+fn is_healthy(started_ms: u64) -> bool {
+    let max_age = HEALTH_MAX_AGE_CYCLES * INTERVAL_MS;
+    let last = G_LAST_CYCLE_MS.load(Ordering::Relaxed).max(started_ms);
+    now_ms().saturating_sub(last) <= max_age
+}
+
+fn handle(stream: TcpStream, started_ms: u64) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_millis(HEALTH_READ_TIMEOUT_MS)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/healthz")) if is_healthy(started_ms) => ("200 OK", "ok\n"),
+        (Some("GET"), Some("/healthz")) => ("503 Service Unavailable", "stale\n"),
+        _ => ("404 Not Found", ""),
+    };
+    let resp = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    (&stream).write_all(resp.as_bytes())
+}
+
+pub fn start_health_server_t(addr: SocketAddr) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let started_ms = now_ms();
+    info!("healthz listening on {}", addr);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(err) = handle(stream, started_ms) {
+                debug!("healthz => {:?}", err);
+            }
+        }
+    });
+    Ok(())
+}
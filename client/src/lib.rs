@@ -0,0 +1,6 @@
+#![deny(warnings)]
+pub mod crypto;
+pub mod output;
+pub mod procfs;
+pub mod proxy;
+pub mod units;
@@ -6,22 +6,50 @@ use clap::Parser;
 use hyper::header;
 use once_cell::sync::Lazy;
 use prost::Message;
+use std::env;
+use std::io::Write;
 use std::net::ToSocketAddrs;
+use std::path::PathBuf;
 use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
 use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 use std::time::{SystemTime, UNIX_EPOCH};
 use sysinfo::{System, SystemExt};
 use tokio::time;
 
+use stat_client::crypto;
+use stat_client::output::{FileOutput, NullOutput, Output, StdoutOutput};
+use stat_client::procfs;
+use stat_client::proxy;
+use stat_client::units;
+#[cfg(feature = "bincode-transport")]
+use stat_common::bin_frame;
+use stat_common::packed;
 use stat_common::server_status::{IpInfo, StatRequest, SysInfo};
 type GenericError = Box<dyn std::error::Error + Send + Sync>;
 type Result<T> = std::result::Result<T, GenericError>;
+mod alert;
+mod clock;
 mod grpc;
+#[cfg_attr(windows, allow(dead_code))]
+mod hdd;
+mod health;
 mod ip_api;
+mod ntp;
+#[cfg(unix)]
+mod plugin;
+#[cfg(feature = "quic")]
+mod quic;
+#[cfg_attr(windows, allow(dead_code))]
 mod status;
 mod sys_info;
+mod syslog;
+mod validate;
+#[cfg(windows)]
+mod win;
 
 const INTERVAL_MS: u64 = 1000;
 
@@ -32,45 +60,773 @@ pub struct ClientConfig {
 }
 
 pub static G_CONFIG: Lazy<Mutex<ClientConfig>> = Lazy::new(|| Mutex::new(ClientConfig::default()));
+// rtt of the last acked report, sent along with the next one
+pub static G_REPORT_RTT_MS: AtomicU64 = AtomicU64::new(0);
 
 #[derive(Parser, Debug, Clone)]
 #[clap(author, version = env!("APP_VERSION"), about, long_about = None)]
 pub struct Args {
-    #[clap(short, long, default_value = "http://127.0.0.1:8080/report")]
+    #[clap(
+        short,
+        long,
+        default_value = "http://127.0.0.1:8080/report",
+        env = "STAT_CLIENT_ADDR"
+    )]
     addr: String,
-    #[clap(short, long, default_value = "h1", help = "username")]
+    #[clap(
+        short,
+        long,
+        default_value = "h1",
+        help = "username",
+        env = "STAT_CLIENT_USER"
+    )]
     user: String,
-    #[clap(short, long, default_value = "p1", help = "password")]
-    pass: String,
-    #[clap(short = 'n', long, help = "enable vnstat, default:false")]
+    #[clap(
+        short,
+        long,
+        default_value = "p1",
+        help = "password",
+        env = "STAT_CLIENT_PASS",
+        hide_env_values = true
+    )]
+    pass: Secret,
+    #[clap(
+        short = 'n',
+        long,
+        help = "enable vnstat, default:false",
+        env = "STAT_CLIENT_VNSTAT"
+    )]
     vnstat: bool,
+    #[clap(
+        long = "vnstat-path",
+        default_value = "/usr/bin/vnstat",
+        parse(from_os_str),
+        help = "vnstat binary, falls back to $PATH if missing",
+        env = "STAT_CLIENT_VNSTAT_PATH"
+    )]
+    vnstat_path: PathBuf,
+    #[clap(
+        long = "traffic-nft",
+        use_value_delimiter = true,
+        help = "traffic from nftables/iptables rule counters, table:chain:comment, IN[,OUT]",
+        env = "STAT_CLIENT_TRAFFIC_NFT"
+    )]
+    traffic_nft: Vec<String>,
+    #[clap(
+        long = "ntp-server",
+        default_value = "pool.ntp.org",
+        help = "ntp server for the clock drift check",
+        env = "STAT_CLIENT_NTP_SERVER"
+    )]
+    ntp_server: String,
+    #[clap(
+        long = "no-ntp-check",
+        help = "disable the clock drift check, default:false",
+        env = "STAT_CLIENT_NO_NTP_CHECK"
+    )]
+    no_ntp_check: bool,
+    #[clap(
+        long = "proc-root",
+        help = "read procfs from this dir instead of /proc, eg: a host /proc mounted into a container",
+        env = "STAT_CLIENT_PROC_ROOT"
+    )]
+    proc_root: Option<PathBuf>,
+    #[clap(
+        long = "net-ns-pid",
+        help = "traffic and connectivity from the net namespace of this pid, eg: 1 for the host from a container",
+        env = "STAT_CLIENT_NET_NS_PID"
+    )]
+    net_ns_pid: Option<u32>,
+    #[clap(
+        long = "netns",
+        parse(try_from_str = status::parse_netns_name),
+        conflicts_with = "net-ns-pid",
+        help = "traffic and connectivity from the named net namespace, /var/run/netns/<name> as `ip netns` creates it",
+        env = "STAT_CLIENT_NETNS"
+    )]
+    netns: Option<String>,
+    #[clap(
+        long = "traffic-prefer",
+        use_value_delimiter = true,
+        help = "traffic source preference, probed in order at startup, nft,vnstat,proc or auto",
+        env = "STAT_CLIENT_TRAFFIC_PREFER"
+    )]
+    traffic_prefer: Vec<String>,
     #[clap(
         long = "disable-extra",
-        help = "disable extra info report, default:false"
+        help = "disable extra info report, default:false",
+        env = "STAT_CLIENT_DISABLE_EXTRA"
     )]
     disable_extra: bool,
-    #[clap(long = "ip-info", help = "show ip info, default:false")]
+    #[clap(
+        long = "ip-info",
+        help = "show ip info, default:false",
+        env = "STAT_CLIENT_IP_INFO"
+    )]
     ip_info: bool,
-    #[clap(long = "json", help = "use json protocol, default:false")]
+    #[clap(
+        long = "json",
+        help = "use json protocol, default:false",
+        env = "STAT_CLIENT_JSON"
+    )]
     json: bool,
-    #[clap(short = '6', long = "ipv6", help = "ipv6 only, default:false")]
+    #[clap(
+        long = "serialization",
+        arg_enum,
+        help = "report body format, json or msgpack (feature msgpack), protobuf when unset",
+        env = "STAT_CLIENT_SERIALIZATION"
+    )]
+    serialization: Option<Serialization>,
+    #[clap(
+        long = "wire-format",
+        arg_enum,
+        help = "http report body, binary is a fixed layout of the core fields only (see common/src/packed.rs), bincode a SRST framed full report (feature bincode-transport), --serialization decides when unset",
+        env = "STAT_CLIENT_WIRE_FORMAT"
+    )]
+    wire_format: Option<WireFormat>,
+    #[clap(
+        long = "warmup",
+        arg_enum,
+        default_value = "wait",
+        help = "before the first report, wait for the cpu/net collectors, skip one report cycle or none",
+        env = "STAT_CLIENT_WARMUP"
+    )]
+    warmup: Warmup,
+    #[clap(
+        long = "warmup-timeout-ms",
+        default_value = "3000",
+        help = "max wait for --warmup wait",
+        env = "STAT_CLIENT_WARMUP_TIMEOUT_MS"
+    )]
+    warmup_timeout_ms: u64,
+    #[clap(
+        short = '6',
+        long = "ipv6",
+        help = "ipv6 only, default:false",
+        env = "STAT_CLIENT_IPV6"
+    )]
     ipv6: bool,
+    #[clap(
+        long = "zfs-arc-cache",
+        help = "treat zfs arc as cache, exclude it from memory used, default:false",
+        env = "STAT_CLIENT_ZFS_ARC_CACHE"
+    )]
+    zfs_arc_cache: bool,
+    #[clap(
+        long = "collect",
+        use_value_delimiter = true,
+        help = "collectors allowlist, uptime,load,cpu,mem,hdd,traffic,net,disk_io,irq,conntrack,entropy,tcp,reboot,kthreads,lockup,power, default:all",
+        env = "STAT_CLIENT_COLLECT"
+    )]
+    collect: Vec<String>,
+    #[clap(
+        long = "profile",
+        help = "profile each collector cost then exit, default:false",
+        env = "STAT_CLIENT_PROFILE"
+    )]
+    profile: bool,
+    #[clap(
+        long = "profile-rounds",
+        default_value = "10",
+        help = "profile rounds per collector",
+        env = "STAT_CLIENT_PROFILE_ROUNDS"
+    )]
+    profile_rounds: usize,
+    #[clap(
+        long = "agent-max-rss-mb",
+        default_value = "100",
+        help = "warn when agent rss exceeds this limit",
+        env = "STAT_CLIENT_AGENT_MAX_RSS_MB"
+    )]
+    agent_max_rss_mb: u64,
+    #[clap(
+        long = "log-level",
+        default_value = "info",
+        possible_values = &["error", "warn", "info", "debug", "trace"],
+        help = "log level",
+        env = "STAT_CLIENT_LOG_LEVEL",
+    )]
+    log_level: log::LevelFilter,
+    #[clap(
+        long = "log-format",
+        arg_enum,
+        default_value = "text",
+        help = "text, or json lines with a collector field",
+        env = "STAT_CLIENT_LOG_FORMAT"
+    )]
+    log_format: LogFormat,
+    #[clap(
+        long = "output",
+        default_value = "tcp",
+        parse(try_from_str = parse_output),
+        help = "tcp reports to --addr, or stdout, file:<path> as json lines, null for benchmarking",
+        env = "STAT_CLIENT_OUTPUT",
+    )]
+    output: OutputTarget,
+    #[clap(
+        long = "raw-counters",
+        help = "report raw cumulative rx/tx bytes with timestamp, default:false",
+        env = "STAT_CLIENT_RAW_COUNTERS"
+    )]
+    raw_counters: bool,
+    #[clap(
+        long = "report-addresses",
+        help = "report ip addresses of each interface, default:false",
+        env = "STAT_CLIENT_REPORT_ADDRESSES"
+    )]
+    report_addresses: bool,
+    #[clap(
+        long = "load-window-secs",
+        default_value = "0",
+        help = "report load_custom, running + blocked tasks averaged over this window, sampled every 2s, 0 disables",
+        env = "STAT_CLIENT_LOAD_WINDOW_SECS"
+    )]
+    load_window_secs: u64,
+    #[clap(
+        long = "cpu-avg-samples",
+        default_value = "1",
+        help = "average cpu percent over the last N samples",
+        env = "STAT_CLIENT_CPU_AVG_SAMPLES"
+    )]
+    cpu_avg_samples: usize,
+    #[clap(
+        long = "top-procs",
+        default_value = "0",
+        help = "report the top N processes by cpu, max 10, 0 disables",
+        env = "STAT_CLIENT_TOP_PROCS"
+    )]
+    top_procs: usize,
+    #[clap(
+        long = "top-talkers",
+        default_value = "0",
+        help = "report the top N remote ips by established tcp connections, max 10, 0 disables",
+        env = "STAT_CLIENT_TOP_TALKERS"
+    )]
+    top_talkers: usize,
+    #[clap(
+        long = "include-dmesg-errors",
+        default_value = "0",
+        help = "report the last N kernel log lines with error/fail/bug, max 20, 0 disables",
+        env = "STAT_CLIENT_INCLUDE_DMESG_ERRORS"
+    )]
+    include_dmesg_errors: usize,
+    #[clap(
+        long = "no-proc-names",
+        help = "report top processes as process_<pid>, default:false",
+        env = "STAT_CLIENT_NO_PROC_NAMES"
+    )]
+    no_proc_names: bool,
+    #[clap(
+        long = "steal-threshold",
+        default_value = "10",
+        help = "cpu steal percent, sustained over 10s, that flags the vm as contended",
+        env = "STAT_CLIENT_STEAL_THRESHOLD"
+    )]
+    steal_threshold: f64,
+    #[clap(
+        long = "load-warn",
+        help = "load_1 above this logs a warning and reports load_alert=warn, default:0.8 x cpus",
+        env = "STAT_CLIENT_LOAD_WARN"
+    )]
+    load_warn: Option<f64>,
+    #[clap(
+        long = "load-crit",
+        help = "load_1 above this logs an error and reports load_alert=critical, default:1.5 x cpus",
+        env = "STAT_CLIENT_LOAD_CRIT"
+    )]
+    load_crit: Option<f64>,
+    #[clap(
+        long = "hdd-statfs",
+        help = "use statfs on /proc/mounts instead of df for hdd, default:false",
+        env = "STAT_CLIENT_HDD_STATFS"
+    )]
+    hdd_statfs: bool,
+    #[clap(
+        long = "hdd-mount",
+        use_value_delimiter = true,
+        help = "mount points hdd totals cover, `/` alone skips df for one statvfs, eg: /,/data",
+        env = "STAT_CLIENT_HDD_MOUNT"
+    )]
+    hdd_mount: Vec<String>,
+    #[clap(
+        long = "byte-units",
+        help = "report memory, swap and hdd in bytes instead of KiB/MiB, default:false",
+        env = "STAT_CLIENT_BYTE_UNITS"
+    )]
+    byte_units: bool,
+    #[clap(
+        long = "control-file",
+        help = "maintenance while this file exists, checked every cycle, eg: /run/serverstat.pause",
+        env = "STAT_CLIENT_CONTROL_FILE"
+    )]
+    control_file: Option<PathBuf>,
+    #[clap(
+        long = "pause-mode",
+        arg_enum,
+        default_value = "flag",
+        help = "during maintenance, flag the reports or skip sending them",
+        env = "STAT_CLIENT_PAUSE_MODE"
+    )]
+    pause_mode: PauseMode,
+    #[clap(
+        long = "probe-disk-latency",
+        help = "dir to write + fsync a small probe file in every 10s, eg: /var/lib",
+        env = "STAT_CLIENT_PROBE_DISK_LATENCY"
+    )]
+    probe_disk_latency: Option<PathBuf>,
+    #[clap(
+        long = "numa-stats",
+        help = "report per numa node memory, default:false",
+        env = "STAT_CLIENT_NUMA_STATS"
+    )]
+    numa_stats: bool,
+    #[clap(
+        long = "systemd",
+        help = "report failed systemd units, default:false",
+        env = "STAT_CLIENT_SYSTEMD"
+    )]
+    systemd: bool,
+    #[clap(
+        long = "rpi",
+        help = "report raspberry pi throttling, auto detected from the device tree, default:false",
+        env = "STAT_CLIENT_RPI"
+    )]
+    rpi: bool,
+    #[clap(
+        long = "quic-endpoint",
+        help = "experimental, report over quic to host:port, needs feature quic",
+        env = "STAT_CLIENT_QUIC_ENDPOINT"
+    )]
+    quic_endpoint: Option<String>,
+    #[clap(
+        long = "ipv6-literal-probe",
+        default_value = "[2001:4860:4860::8888]:53",
+        help = "ipv6 connectivity probe, a literal ip:port tcp connect, no dns involved",
+        env = "STAT_CLIENT_IPV6_LITERAL_PROBE"
+    )]
+    ipv6_literal_probe: String,
+    #[clap(
+        long = "ipv6-dns-probe",
+        help = "also probe ipv6 via ipv6.google.com:80, depends on dns resolution, default:false",
+        env = "STAT_CLIENT_IPV6_DNS_PROBE"
+    )]
+    ipv6_dns_probe: bool,
+    #[clap(
+        long = "plugin-dir",
+        parse(from_os_str),
+        help = "run every executable here each cycle, `key value` output lines go to custom_metrics",
+        env = "STAT_CLIENT_PLUGIN_DIR"
+    )]
+    plugin_dir: Option<PathBuf>,
+    #[clap(
+        long = "plugin-timeout-ms",
+        default_value = "3000",
+        help = "per plugin timeout, killed when exceeded",
+        env = "STAT_CLIENT_PLUGIN_TIMEOUT_MS"
+    )]
+    plugin_timeout_ms: u64,
+    #[clap(
+        long = "field-map",
+        use_value_delimiter = true,
+        help = "rename json fields, old=new, dotted paths for nested, eg: custom_metrics.foo=foo",
+        env = "STAT_CLIENT_FIELD_MAP"
+    )]
+    field_map: Vec<String>,
+    #[clap(
+        long = "watch-partition",
+        multiple_occurrences = true,
+        parse(try_from_str = parse_watch_partition),
+        help = "PATH:THRESHOLD_PCT, warn and report in critical_partitions when exceeded, repeatable",
+        env = "STAT_CLIENT_WATCH_PARTITION",
+    )]
+    watch_partition: Vec<(String, f64)>,
+    #[clap(
+        long = "alert-rule",
+        multiple_occurrences = true,
+        parse(try_from_str = alert::parse_alert_rule),
+        help = "metric:op:value:duration, eg: cpu:>:95:60, hdd_pct:>:90:0, repeatable",
+        env = "STAT_CLIENT_ALERT_RULE",
+    )]
+    alert_rule: Vec<alert::AlertRule>,
+    #[clap(
+        long = "syslog-alert",
+        help = "log mem/hdd > 95% and load_1 > 2 x cpus to /dev/log once on crossing, unix only, default:false",
+        env = "STAT_CLIENT_SYSLOG_ALERT"
+    )]
+    syslog_alert: bool,
+    #[clap(
+        long = "syslog-facility",
+        default_value = "daemon",
+        parse(try_from_str = syslog::parse_facility),
+        help = "syslog facility for --syslog-alert, eg: daemon, local0",
+        env = "STAT_CLIENT_SYSLOG_FACILITY"
+    )]
+    syslog_facility: u8,
+    #[clap(
+        long = "alert-webhook",
+        help = "POST json alerts here",
+        env = "STAT_CLIENT_ALERT_WEBHOOK"
+    )]
+    alert_webhook: Option<String>,
+    #[clap(
+        long = "alert-cooldown",
+        default_value = "600",
+        help = "min secs between two alerts of the same rule",
+        env = "STAT_CLIENT_ALERT_COOLDOWN"
+    )]
+    alert_cooldown: u64,
+    #[clap(
+        long = "encrypt-key",
+        parse(try_from_str = parse_encrypt_key),
+        help = "encrypt http report body with chacha20poly1305, 32 bytes key as hex or base64",
+        env = "STAT_CLIENT_ENCRYPT_KEY",
+        hide_env_values = true
+    )]
+    encrypt_key: Option<Secret<[u8; 32]>>,
+    #[clap(
+        long = "proxy",
+        help = "http report via proxy, socks5://[user:pass@]host:port or http://[user:pass@]host:port, without it HTTP_PROXY/HTTPS_PROXY/NO_PROXY apply to http and grpc, the network probes connect directly",
+        env = "STAT_CLIENT_PROXY"
+    )]
+    proxy: Option<proxy::ProxyUrl>,
+    #[clap(
+        long = "http-username",
+        help = "basic auth for a reverse proxy in front of the server, the -u/-p auth moves to the X-Node-Authorization header",
+        env = "STAT_CLIENT_HTTP_USERNAME"
+    )]
+    http_username: Option<String>,
+    #[clap(
+        long = "http-password",
+        default_value = "",
+        hide_default_value = true,
+        env = "STAT_CLIENT_HTTP_PASSWORD",
+        hide_env_values = true
+    )]
+    http_password: Secret,
+    #[clap(
+        long = "health-listen",
+        help = "serve GET /healthz on this addr, 503 once reports stall, eg: 127.0.0.1:9395",
+        env = "STAT_CLIENT_HEALTH_LISTEN"
+    )]
+    health_listen: Option<std::net::SocketAddr>,
+    #[clap(
+        long = "seccomp-safe",
+        help = "only read /proc and /sys, no subprocesses (vnstat, df, dmesg, ...) and no probe connects, default:false",
+        env = "STAT_CLIENT_SECCOMP_SAFE"
+    )]
+    seccomp_safe: bool,
+    #[clap(
+        long = "heartbeat-interval",
+        default_value = "0",
+        help = "secs between liveness only reports (name, uptime, ts, seq), full reports then go every --full-interval, 0 disables",
+        env = "STAT_CLIENT_HEARTBEAT_INTERVAL"
+    )]
+    heartbeat_interval: u64,
+    #[clap(
+        long = "full-interval",
+        default_value = "60",
+        help = "secs between full reports when --heartbeat-interval is set",
+        env = "STAT_CLIENT_FULL_INTERVAL"
+    )]
+    full_interval: u64,
+    #[clap(
+        long = "max-payload-bytes",
+        default_value = "0",
+        help = "drop optional extras until the report fits, 0 means no cap",
+        env = "STAT_CLIENT_MAX_PAYLOAD_BYTES"
+    )]
+    max_payload_bytes: usize,
+}
+
+// eg: /var/log:90
+fn parse_watch_partition(s: &str) -> std::result::Result<(String, f64), String> {
+    let (path, pct) = s
+        .rsplit_once(':')
+        .ok_or_else(|| format!("expect PATH:THRESHOLD_PCT, got `{}`", s))?;
+    let pct = pct
+        .parse::<f64>()
+        .map_err(|err| format!("invalid threshold `{}`, {}", pct, err))?;
+    Ok((path.to_string(), pct))
+}
+
+#[derive(clap::ArgEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Serialization {
+    Json,
+    Msgpack,
+}
+
+#[derive(clap::ArgEnum, Debug, Clone, Copy, PartialEq)]
+pub enum WireFormat {
+    Binary,
+    Bincode,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputTarget {
+    // http, grpc or quic, picked by --addr / --quic-endpoint
+    Tcp,
+    Stdout,
+    File(PathBuf),
+    Null,
+}
+
+fn parse_output(s: &str) -> std::result::Result<OutputTarget, String> {
+    match s {
+        "tcp" => Ok(OutputTarget::Tcp),
+        "stdout" => Ok(OutputTarget::Stdout),
+        "null" => Ok(OutputTarget::Null),
+        _ => match s.strip_prefix("file:") {
+            Some(path) if !path.is_empty() => Ok(OutputTarget::File(PathBuf::from(path))),
+            _ => Err(format!(
+                "expect tcp, stdout, file:<path> or null, got `{}`",
+                s
+            )),
+        },
+    }
+}
+
+#[derive(clap::ArgEnum, Debug, Clone, Copy, PartialEq)]
+pub enum PauseMode {
+    Flag,
+    Skip,
+}
+
+#[derive(clap::ArgEnum, Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+// cpu percent and net speed need two samples, the first report reads 0 otherwise
+#[derive(clap::ArgEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Warmup {
+    None,
+    Wait,
+    Skip,
+}
+
+// never shows up in logs, Debug prints `***`
+#[derive(Clone, Default)]
+pub struct Secret<T = String>(T);
+
+impl<T> std::fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl std::str::FromStr for Secret {
+    type Err = std::convert::Infallible;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(Secret(s.to_string()))
+    }
+}
+
+fn parse_encrypt_key(s: &str) -> std::result::Result<Secret<[u8; 32]>, String> {
+    crypto::parse_key(s).map(Secret)
+}
+
+impl Args {
+    pub fn collect_enabled(&self, name: &str) -> bool {
+        self.collect.is_empty() || self.collect.iter().any(|s| s.eq(name))
+    }
+
+    // (warn, crit) for load_1
+    pub fn load_thresholds(&self) -> (f64, f64) {
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get()) as f64;
+        (
+            self.load_warn.unwrap_or(cpus * 0.8),
+            self.load_crit.unwrap_or(cpus * 1.5),
+        )
+    }
+
+    pub fn net_ns(&self) -> Option<status::NetNs> {
+        match (self.net_ns_pid, &self.netns) {
+            (Some(pid), _) => Some(status::NetNs::Pid(pid)),
+            (None, Some(name)) => Some(status::NetNs::Named(name.to_string())),
+            (None, None) => None,
+        }
+    }
+
+    pub fn use_json(&self) -> bool {
+        self.json || self.serialization == Some(Serialization::Json)
+    }
+
+    pub fn use_msgpack(&self) -> bool {
+        self.serialization == Some(Serialization::Msgpack)
+    }
+
+    pub fn in_maintenance(&self) -> bool {
+        self.control_file
+            .as_ref()
+            .map_or(false, |path| path.exists())
+    }
+
+    // --seccomp-safe, turn off every option that forks or connects besides the report itself
+    fn apply_seccomp_safe(&mut self) {
+        if !self.seccomp_safe {
+            return;
+        }
+        if self.vnstat || !self.traffic_nft.is_empty() || !self.traffic_prefer.is_empty() {
+            warn!("--seccomp-safe, traffic from /proc/net/dev only");
+        }
+        self.vnstat = false;
+        self.traffic_nft.clear();
+        self.traffic_prefer.clear();
+        // df for anything but `/` alone
+        if !self.hdd_mount.is_empty() && self.hdd_mount != ["/"] {
+            warn!("--seccomp-safe, --hdd-mount ignored, statfs over /proc/mounts");
+            self.hdd_mount.clear();
+        }
+        self.hdd_statfs = true;
+        self.systemd = false;
+        self.plugin_dir = None;
+        self.no_ntp_check = true;
+        self.disable_extra = true;
+    }
+
+    // --pause-mode skip, the reporters send nothing while the control file exists
+    pub fn skip_report(&self) -> bool {
+        self.pause_mode == PauseMode::Skip && self.in_maintenance()
+    }
+}
+
+#[cfg(all(feature = "native", not(feature = "sysinfo"), not(windows)))]
+fn wait_warmup(args: &Args) {
+    let timeout = Duration::from_millis(args.warmup_timeout_ms);
+    if !status::wait_warmup(
+        args.collect_enabled("cpu"),
+        args.collect_enabled("net"),
+        timeout,
+    ) {
+        warn!("collectors not warm after {:?}, reporting anyway", timeout);
+    }
+}
+
+// only the native linux collectors flag warm, the others get one cycle
+#[cfg(not(all(feature = "native", not(feature = "sysinfo"), not(windows))))]
+fn wait_warmup(_args: &Args) {
+    thread::sleep(Duration::from_millis(INTERVAL_MS));
+}
+
+// None for tcp, the network reporters own that path
+fn open_output(target: &OutputTarget) -> std::io::Result<Option<Box<dyn Output>>> {
+    Ok(match target {
+        OutputTarget::Tcp => None,
+        OutputTarget::Stdout => Some(Box::new(StdoutOutput)),
+        OutputTarget::File(path) => Some(Box::new(FileOutput::open(path)?)),
+        OutputTarget::Null => Some(Box::new(NullOutput)),
+    })
+}
+
+fn local_report(args: &Args, stat_base: &StatRequest, mut out: Box<dyn Output>) -> Result<()> {
+    let mut schedule = ReportSchedule::default();
+    loop {
+        // a paused loop still counts as alive
+        health::mark_cycle();
+        if args.skip_report() {
+            thread::sleep(Duration::from_millis(INTERVAL_MS));
+            continue;
+        }
+        let stat_rt = schedule.next(args, stat_base);
+        if let Err(err) = out.write(&stat_rt) {
+            error!("{}", err);
+        }
+
+        thread::sleep(report_interval(args));
+    }
+}
+
+// --heartbeat-interval, liveness only reports between the full ones
+#[derive(Debug, Default)]
+pub struct ReportSchedule {
+    // when the last full report was sampled, and its uptime
+    last_full: Option<(Instant, u64)>,
+    seq: u64,
+}
+
+impl ReportSchedule {
+    pub fn next(&mut self, args: &Args, stat_base: &StatRequest) -> StatRequest {
+        self.seq += 1;
+        let full_due = args.heartbeat_interval == 0
+            || !self.last_full.map_or(false, |(at, _)| {
+                at.elapsed() < Duration::from_secs(args.full_interval)
+            });
+        let mut stat_rt = match self.last_full {
+            Some((at, uptime)) if !full_due => {
+                heartbeat(stat_base, uptime + at.elapsed().as_secs())
+            }
+            _ => {
+                let stat_rt = sample_all(args, stat_base);
+                self.last_full = Some((Instant::now(), stat_rt.uptime));
+                stat_rt
+            }
+        };
+        stat_rt.seq = self.seq;
+        stat_rt
+    }
+}
+
+// sleep between two report cycles
+pub fn report_interval(args: &Args) -> Duration {
+    if args.heartbeat_interval > 0 {
+        Duration::from_secs(args.heartbeat_interval)
+    } else {
+        Duration::from_millis(INTERVAL_MS)
+    }
+}
+
+// name, uptime and timestamp, the server keeps the last full report's metrics
+fn heartbeat(stat_base: &StatRequest, uptime: u64) -> StatRequest {
+    StatRequest {
+        name: stat_base.name.to_string(),
+        frame: "heartbeat".to_string(),
+        online4: stat_base.online4,
+        online6: stat_base.online6,
+        uptime,
+        latest_ts: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        ..Default::default()
+    }
 }
 
 fn sample_all(args: &Args, stat_base: &StatRequest) -> StatRequest {
-    // dbg!(&stat_base);
     let mut stat_rt = stat_base.clone();
 
-    #[cfg(all(feature = "native", not(feature = "sysinfo")))]
+    #[cfg(all(feature = "native", not(feature = "sysinfo"), not(windows)))]
     status::sample(args, &mut stat_rt);
+    #[cfg(all(feature = "native", not(feature = "sysinfo"), windows))]
+    win::sample(args, &mut stat_rt);
     #[cfg(all(feature = "sysinfo", not(feature = "native")))]
     sys_info::sample(args, &mut stat_rt);
+    validate::sanitize(&mut stat_rt);
+    if args.byte_units {
+        stat_rt.byte_units = true;
+    } else {
+        units::to_legacy_units(&mut stat_rt);
+    }
 
     stat_rt.latest_ts = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs();
 
+    stat_rt.report_rtt_ms = G_REPORT_RTT_MS.load(Ordering::Relaxed);
+    if !args.no_ntp_check {
+        if let Ok(o) = ntp::G_NTP_OFFSET_MS.lock() {
+            stat_rt.ntp_offset_ms = o.unwrap_or(0);
+        }
+    }
+
+    // maintenance, the dashboard shows it instead of alerting
+    stat_rt.maintenance = args.in_maintenance();
+    if !stat_rt.maintenance {
+        alert::evaluate(args, &stat_rt);
+        alert::evaluate_syslog(args, &stat_rt);
+    }
+
     if !args.disable_extra {
         if let Ok(o) = G_CONFIG.lock() {
             if let Some(ip_info) = o.ip_info.as_ref() {
@@ -82,9 +838,161 @@ fn sample_all(args: &Args, stat_base: &StatRequest) -> StatRequest {
         }
     }
 
+    cap_payload(args, &mut stat_rt);
+
+    trace!("stat => {:?}", stat_rt);
+
     stat_rt
 }
 
+// lowest priority first, the core metrics are never dropped
+const PAYLOAD_DROP_ORDER: &[fn(&mut StatRequest)] = &[
+    |stat| stat.collector_status.clear(),
+    |stat| stat.custom_metrics.clear(),
+    |stat| stat.iface_addresses.clear(),
+    |stat| stat.iface_links.clear(),
+    |stat| stat.disk_await_ms.clear(),
+    |stat| stat.disks.clear(),
+    |stat| stat.kernel_errors.clear(),
+    |stat| stat.top_procs.clear(),
+    |stat| stat.top_talkers.clear(),
+    |stat| stat.numa_memory.clear(),
+    |stat| stat.swap_devices.clear(),
+    |stat| stat.throttle_reasons.clear(),
+    |stat| stat.reboot_required_pkgs.clear(),
+    |stat| stat.failed_units.clear(),
+    |stat| stat.sys_info = None,
+    |stat| stat.ip_info = None,
+];
+
+// bytes on the wire for the transport main() picks, the same order
+fn payload_len(args: &Args, stat: &StatRequest) -> usize {
+    if args.output != OutputTarget::Tcp {
+        // json lines
+        serde_json::to_vec(stat).map_or(0, |v| v.len() + 1)
+    } else if args.quic_endpoint.is_some() || !args.addr.starts_with("http") {
+        stat.encoded_len()
+    } else {
+        encode_http_body(args, stat).map_or(0, |(body, _)| body.len())
+    }
+}
+
+fn cap_payload(args: &Args, stat: &mut StatRequest) {
+    if args.max_payload_bytes == 0 {
+        return;
+    }
+    let size = |stat: &StatRequest| payload_len(args, stat);
+    for drop in PAYLOAD_DROP_ORDER {
+        if size(stat) <= args.max_payload_bytes {
+            return;
+        }
+        drop(stat);
+        stat.truncated = true;
+    }
+    let size = size(stat);
+    if size > args.max_payload_bytes {
+        warn!(
+            "payload {} bytes still over the {} bytes cap",
+            size, args.max_payload_bytes
+        );
+    }
+}
+
+fn take_json_path(value: &mut serde_json::Value, path: &str) -> Option<serde_json::Value> {
+    let (parent, key) = match path.rsplit_once('.') {
+        Some((parent, key)) => (
+            value.pointer_mut(&format!("/{}", parent.replace('.', "/")))?,
+            key,
+        ),
+        None => (value, path),
+    };
+    parent.as_object_mut()?.remove(key)
+}
+
+fn put_json_path(value: &mut serde_json::Value, path: &str, v: serde_json::Value) {
+    let mut cur = value;
+    let mut keys = path.split('.').peekable();
+    while let Some(key) = keys.next() {
+        let obj = match cur.as_object_mut() {
+            Some(obj) => obj,
+            None => return,
+        };
+        if keys.peek().is_none() {
+            obj.insert(key.to_string(), v);
+            return;
+        }
+        cur = obj
+            .entry(key)
+            .or_insert_with(|| serde_json::Value::Object(Default::default()));
+    }
+}
+
+// old=new renames, applied in order, json only
+fn apply_field_map(value: &mut serde_json::Value, field_map: &[String]) {
+    for item in field_map {
+        match item.split_once('=') {
+            Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+                if let Some(v) = take_json_path(value, old) {
+                    put_json_path(value, new, v);
+                }
+            }
+            _ => warn!("invalid field map => {}", item),
+        }
+    }
+}
+
+// `Basic base64(user:pass)`
+fn basic_auth_header(user: &str, pass: &str) -> String {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    format!("Basic {}", STANDARD.encode(format!("{}:{}", user, pass)))
+}
+
+// the http report body and its content type, what --max-payload-bytes measures too
+fn encode_http_body(args: &Args, stat: &StatRequest) -> Result<(Vec<u8>, &'static str)> {
+    let mut body: Vec<u8>;
+    let mut content_type = "application/octet-stream";
+    // the packed layout has no frame, heartbeats go as protobuf
+    if args.wire_format == Some(WireFormat::Binary) && stat.frame != "heartbeat" {
+        // told apart by its magic like the bincode frame
+        body = packed::encode(stat)?;
+    } else if args.wire_format == Some(WireFormat::Bincode) {
+        // the server tells the frame apart by its magic
+        #[cfg(feature = "bincode-transport")]
+        {
+            body = bin_frame::encode(stat)?;
+        }
+        #[cfg(not(feature = "bincode-transport"))]
+        unreachable!("checked at startup");
+    } else if args.use_msgpack() {
+        // named maps, the server decodes schemaless just like json
+        #[cfg(feature = "msgpack")]
+        {
+            body = rmp_serde::to_vec_named(stat)?;
+            content_type = "application/msgpack";
+        }
+        #[cfg(not(feature = "msgpack"))]
+        unreachable!("checked at startup");
+    } else if args.use_json() {
+        let data = if args.field_map.is_empty() {
+            serde_json::to_string(stat)?
+        } else {
+            let mut value = serde_json::to_value(stat)?;
+            apply_field_map(&mut value, &args.field_map);
+            value.to_string()
+        };
+        trace!("json_str => {:?}", serde_json::to_string(&data)?);
+        body = data.into();
+        content_type = "application/json";
+    } else {
+        body = stat.encode_to_vec();
+    }
+    if let Some(Secret(key)) = args.encrypt_key.as_ref() {
+        body = crypto::encrypt(key, &body)?;
+    }
+    Ok((body, content_type))
+}
+
 fn http_report(args: &Args, stat_base: &mut StatRequest) -> Result<()> {
     let mut domain = args.addr.split('/').collect::<Vec<&str>>()[2].to_owned();
     if !domain.contains(':') {
@@ -94,59 +1002,89 @@ fn http_report(args: &Args, stat_base: &mut StatRequest) -> Result<()> {
             domain = format!("{}:80", domain);
         }
     }
-    let tcp_addr = domain.to_socket_addrs()?.next().unwrap();
-    let (ipv4, ipv6) = (tcp_addr.is_ipv4(), tcp_addr.is_ipv6());
-    if ipv4 {
-        stat_base.online4 = ipv4;
-    }
-    if ipv6 {
-        stat_base.online6 = ipv6;
+    // behind a proxy the server name may only resolve on the proxy side,
+    // reqwest picks up HTTP_PROXY/HTTPS_PROXY itself when --proxy is not set
+    let host = domain
+        .rsplit_once(':')
+        .map_or(domain.as_str(), |(host, _)| host);
+    let behind_proxy = args.proxy.is_some()
+        || proxy::env_proxy_for(host, args.addr.starts_with("https")).is_some();
+    match domain.to_socket_addrs() {
+        Ok(mut addrs) => {
+            if let Some(tcp_addr) = addrs.next() {
+                let (ipv4, ipv6) = (tcp_addr.is_ipv4(), tcp_addr.is_ipv6());
+                if ipv4 {
+                    stat_base.online4 = ipv4;
+                }
+                if ipv6 {
+                    stat_base.online6 = ipv6;
+                }
+            }
+        }
+        Err(err) if behind_proxy => {
+            warn!("resolve {} => {:?}, left to the proxy", domain, err);
+        }
+        Err(err) => return Err(err.into()),
     }
 
-    let http_client = reqwest::Client::builder()
+    let mut http_builder = reqwest::Client::builder()
         .pool_max_idle_per_host(1)
         .connect_timeout(Duration::from_secs(5))
         .user_agent(format!(
             "{}/{}",
             env!("CARGO_BIN_NAME"),
             env!("CARGO_PKG_VERSION")
-        ))
-        .build()?;
+        ));
+    // credentials in the proxy url are used for the proxy auth
+    if let Some(proxy) = args.proxy.as_ref() {
+        http_builder = http_builder.proxy(reqwest::Proxy::all(proxy.0.as_str())?);
+    }
+    let http_client = http_builder.build()?;
+    let mut schedule = ReportSchedule::default();
     loop {
-        let stat_rt = sample_all(args, stat_base);
-
-        let body_data: Option<Vec<u8>>;
-        let mut content_type = "application/octet-stream";
-        if args.json {
-            let data = serde_json::to_string(&stat_rt)?;
-            trace!("json_str => {:?}", serde_json::to_string(&data)?);
-            body_data = Some(data.into());
-            content_type = "application/json";
-        } else {
-            let buf = stat_rt.encode_to_vec();
-            body_data = Some(buf);
-            // content_type = "application/octet-stream";
+        // a paused loop still counts as alive
+        health::mark_cycle();
+        if args.skip_report() {
+            thread::sleep(Duration::from_millis(INTERVAL_MS));
+            continue;
         }
+        let stat_rt = schedule.next(args, stat_base);
+
+        let (body, content_type) = encode_http_body(args, &stat_rt)?;
         // byte 581, json str 1281
-        // dbg!(&body_data.as_ref().unwrap().len());
+        debug!("body size => {}", body.len());
 
         let client = http_client.clone();
         let url = args.addr.to_string();
         let auth_user = args.user.to_string();
-        let auth_pass = args.pass.to_string();
+        let auth_pass = args.pass.0.to_string();
+        let encrypted = args.encrypt_key.is_some();
+        let proxy_auth = args
+            .http_username
+            .as_ref()
+            .map(|user| basic_auth_header(user, &args.http_password.0));
 
         // http
         tokio::spawn(async move {
-            match client
+            let mut req = client
                 .post(&url)
-                .basic_auth(auth_user, Some(auth_pass))
                 .timeout(Duration::from_secs(3))
-                .header(header::CONTENT_TYPE, content_type)
-                .body(body_data.unwrap())
-                .send()
-                .await
-            {
+                .header(header::CONTENT_TYPE, content_type);
+            // the proxy takes Authorization, the server checks the node header first
+            req = match proxy_auth {
+                Some(auth) => req.header(header::AUTHORIZATION, auth).header(
+                    stat_common::NODE_AUTH_HEADER,
+                    basic_auth_header(&auth_user, &auth_pass),
+                ),
+                None => req.basic_auth(auth_user, Some(auth_pass)),
+            };
+            if encrypted {
+                req = req.header(crypto::ENCRYPT_HEADER, crypto::ENCRYPT_ALGO);
+            }
+            let start = Instant::now();
+            match req.body(body).send().await {
                 Ok(resp) => {
+                    G_REPORT_RTT_MS.store(start.elapsed().as_millis() as u64, Ordering::Relaxed);
                     info!("report resp => {:?}", resp);
                 }
                 Err(err) => {
@@ -155,7 +1093,7 @@ fn http_report(args: &Args, stat_base: &mut StatRequest) -> Result<()> {
             }
         });
 
-        thread::sleep(Duration::from_millis(INTERVAL_MS));
+        thread::sleep(report_interval(args));
     }
 }
 
@@ -182,16 +1120,71 @@ async fn refresh_ip_info(args: &Args) {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    pretty_env_logger::init();
-    let args = Args::parse();
-    dbg!(&args);
+    let mut args = Args::parse();
+
+    let mut builder = pretty_env_logger::formatted_builder();
+    builder.filter_level(args.log_level);
+    if let Ok(filters) = env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+    if args.log_format == LogFormat::Json {
+        builder.format(|buf, record| {
+            // the module that logged, eg: status, hdd, ntp
+            let module = record.module_path().unwrap_or_default();
+            let line = serde_json::json!({
+                "ts": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                "level": record.level().as_str(),
+                "collector": module.rsplit("::").next().unwrap_or(module),
+                "target": record.target(),
+                "msg": record.args().to_string(),
+            });
+            writeln!(buf, "{}", line)
+        });
+    }
+    builder.init();
+
+    args.apply_seccomp_safe();
+
+    debug!("args => {:#?}", args);
+
+    if let Some(root) = args.proc_root.as_ref() {
+        procfs::set_proc_root(root);
+    }
 
     if args.ip_info {
         let info = ip_api::get_ip_info(args.ipv6).await?;
-        dbg!(info);
+        // the output of --ip-info itself, not a log line
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        process::exit(0);
+    }
+
+    if args.profile {
+        status::profile(&args);
         process::exit(0);
     }
 
+    #[cfg(not(unix))]
+    if args.syslog_alert {
+        eprintln!("--syslog-alert needs /dev/log, unix only");
+        process::exit(1);
+    }
+
+    #[cfg(not(feature = "msgpack"))]
+    if args.use_msgpack() {
+        eprintln!("--serialization msgpack requires the msgpack feature");
+        process::exit(1);
+    }
+
+    #[cfg(not(feature = "bincode-transport"))]
+    if args.wire_format == Some(WireFormat::Bincode) {
+        eprintln!("--wire-format bincode requires the bincode-transport feature");
+        process::exit(1);
+    }
+
+    if let Some(addr) = args.health_listen {
+        health::start_health_server_t(addr)?;
+    }
+
     let sys_info = sys_info::collect_sys_info(&args);
     let sys_info_json = serde_json::to_string(&sys_info)?;
     eprintln!("sys info: {}", sys_info_json);
@@ -206,11 +1199,54 @@ async fn main() -> Result<()> {
     }
 
     // use native
-    #[cfg(all(feature = "native", not(feature = "sysinfo")))]
+    #[cfg(all(feature = "native", not(feature = "sysinfo"), not(windows)))]
     {
         eprintln!("enable feature native");
-        status::start_cpu_percent_collect_t();
-        status::start_net_speed_collect_t();
+        if status::is_in_container() {
+            info!("Running inside container, using cgroup limits");
+        }
+        if args.collect_enabled("traffic") {
+            status::traffic_source(&args);
+        }
+        if args.collect_enabled("load") && args.load_window_secs > 0 {
+            status::start_load_custom_collect_t(args.load_window_secs);
+        }
+        if args.collect_enabled("cpu") {
+            status::start_cpu_percent_collect_t(args.cpu_avg_samples, args.steal_threshold);
+            if args.top_procs > 0 {
+                status::start_top_procs_collect_t(args.top_procs, args.no_proc_names);
+            }
+        }
+        if args.collect_enabled("net") {
+            status::start_net_speed_collect_t(args.net_ns());
+        }
+        if args.collect_enabled("disk_io") {
+            status::start_disk_io_collect_t();
+        }
+        if args.collect_enabled("irq") {
+            status::start_irq_rate_collect_t();
+        }
+        if let Some(path) = args.probe_disk_latency.as_ref() {
+            hdd::start_disk_probe_t(path.to_owned());
+        }
+        if let Some(dir) = args.plugin_dir.as_ref() {
+            plugin::start_plugin_collect_t(
+                dir.to_owned(),
+                Duration::from_millis(args.plugin_timeout_ms),
+            );
+        }
+    }
+
+    // use windows api
+    #[cfg(all(feature = "native", not(feature = "sysinfo"), windows))]
+    {
+        eprintln!("enable feature native, windows");
+        if args.collect_enabled("cpu") {
+            win::start_cpu_percent_collect_t();
+        }
+        if args.collect_enabled("net") {
+            win::start_net_speed_collect_t();
+        }
     }
 
     // use sysinfo
@@ -221,8 +1257,31 @@ async fn main() -> Result<()> {
         sys_info::start_net_speed_collect_t();
     }
 
+    if !args.no_ntp_check {
+        ntp::start_ntp_offset_collect_t(args.ntp_server.to_string());
+    }
+
+    match args.warmup {
+        Warmup::Wait => wait_warmup(&args),
+        Warmup::Skip => thread::sleep(Duration::from_millis(INTERVAL_MS)),
+        Warmup::None => {}
+    }
+
     // status::start_all_ping_collect_t(&args);
-    let (ipv4, ipv6) = status::get_network();
+    let (ipv4, ipv6) = if args.seccomp_safe {
+        info!("--seccomp-safe, skip the connectivity probes");
+        (false, false)
+    } else if let Some(ns) = args.net_ns() {
+        status::in_net_ns(&ns, || {
+            status::get_network(&args.ipv6_literal_probe, args.ipv6_dns_probe)
+        })
+        .unwrap_or_else(|err| {
+            error!("enter net namespace {:?} => {:?}", ns, err);
+            (false, false)
+        })
+    } else {
+        status::get_network(&args.ipv6_literal_probe, args.ipv6_dns_probe)
+    };
     eprintln!("get_network (ipv4, ipv6) => ({}, {})", ipv4, ipv6);
 
     if !args.disable_extra {
@@ -240,15 +1299,69 @@ async fn main() -> Result<()> {
         ..Default::default()
     };
 
-    if args.addr.starts_with("http") {
-        let result = http_report(&args, &mut stat_base);
-        dbg!(&result);
+    if let Some(out) = open_output(&args.output)? {
+        if let Err(err) = local_report(&args, &stat_base, out) {
+            error!("local report => {:?}", err);
+        }
+    } else if args.quic_endpoint.is_some() {
+        #[cfg(feature = "quic")]
+        {
+            if let Err(err) = quic::report(&args, &mut stat_base).await {
+                error!("quic report => {:?}", err);
+            }
+        }
+        #[cfg(not(feature = "quic"))]
+        eprintln!("quic-endpoint needs feature quic!");
+    } else if args.addr.starts_with("http") {
+        if let Err(err) = http_report(&args, &mut stat_base) {
+            error!("http report => {:?}", err);
+        }
     } else if args.addr.starts_with("grpc") {
-        let result = grpc::report(&args, &mut stat_base).await;
-        dbg!(&result);
+        if let Err(err) = grpc::report(&args, &mut stat_base).await {
+            error!("grpc report => {:?}", err);
+        }
     } else {
-        eprint!("invalid addr scheme!");
+        eprintln!("invalid addr scheme!");
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_auth_header_value() {
+        assert_eq!(
+            basic_auth_header("Aladdin", "open sesame"),
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+        assert_eq!(format!("{:?}", Secret("p1".to_string())), "***");
+        assert_eq!(format!("{:?}", Secret([7_u8; 32])), "***");
+    }
+
+    #[test]
+    fn payload_len_counts_the_encryption() {
+        let key = "ab".repeat(32);
+        let args = Args::parse_from(["stat_client", "--encrypt-key", key.as_str()]);
+        let stat = StatRequest {
+            name: "h1".to_string(),
+            ..Default::default()
+        };
+        // 12 bytes nonce + 16 bytes tag around the protobuf
+        assert_eq!(payload_len(&args, &stat), stat.encoded_len() + 28);
+
+        let args = Args::parse_from(["stat_client", "--json", "--output", "stdout"]);
+        assert_eq!(
+            payload_len(&args, &stat),
+            serde_json::to_vec(&stat).unwrap().len() + 1
+        );
+    }
+
+    #[test]
+    fn args_are_consistent() {
+        use clap::CommandFactory;
+        Args::command().debug_assert();
+    }
+}
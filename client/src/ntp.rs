@@ -0,0 +1,106 @@
+// minimal sntp (rfc 4330) client, local clock offset against an ntp server
+use lazy_static::lazy_static;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NTP_PORT: u16 = 123;
+const NTP_PACKET_LEN: usize = 48;
+// 1900-01-01 to 1970-01-01
+const NTP_UNIX_DELTA_SECS: u64 = 2_208_988_800;
+const NTP_SAMPLES: usize = 4;
+const NTP_TIMEOUT_MS: u64 = 1000;
+const NTP_INTERVAL_SECS: u64 = 600;
+const NTP_DRIFT_WARN_MS: i64 = 500;
+
+lazy_static! {
+    pub static ref G_NTP_OFFSET_MS: Arc<Mutex<Option<i64>>> = Arc::new(Default::default());
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+// 64 bit ntp timestamp, 32.32 fixed point secs since 1900, to unix ms
+fn ntp_ts_to_unix_ms(b: &[u8]) -> i64 {
+    let secs = u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as i64;
+    let frac = u32::from_be_bytes([b[4], b[5], b[6], b[7]]) as i64;
+    (secs - NTP_UNIX_DELTA_SECS as i64) * 1000 + ((frac * 1000) >> 32)
+}
+
+// (offset, round trip delay) ms, t0 and t3 are the local send/recv times
+pub fn parse_sntp_response(buf: &[u8], t0: i64, t3: i64) -> Option<(i64, i64)> {
+    if buf.len() < NTP_PACKET_LEN {
+        return None;
+    }
+    // mode 4 is a server reply, stratum 0 a kiss-o'-death, above 15 unsynchronized
+    let mode = buf[0] & 0x07;
+    let stratum = buf[1];
+    if mode != 4 || stratum == 0 || stratum > 15 {
+        return None;
+    }
+    let t1 = ntp_ts_to_unix_ms(&buf[32..40]);
+    let t2 = ntp_ts_to_unix_ms(&buf[40..48]);
+    let offset = ((t1 - t0) + (t2 - t3)) / 2;
+    let delay = (t3 - t0) - (t2 - t1);
+    Some((offset, delay))
+}
+
+// the sample with the shortest round trip wins, None when every query failed
+pub fn get_clock_offset_ms(server: &str) -> Option<i64> {
+    let addr = (server, NTP_PORT).to_socket_addrs().ok()?.next()?;
+    let bind_addr = if addr.is_ipv6() {
+        "[::]:0"
+    } else {
+        "0.0.0.0:0"
+    };
+    let socket = UdpSocket::bind(bind_addr).ok()?;
+    socket
+        .set_read_timeout(Some(Duration::from_millis(NTP_TIMEOUT_MS)))
+        .ok()?;
+    socket.connect(addr).ok()?;
+
+    let mut best: Option<(i64, i64)> = None;
+    for _ in 0..NTP_SAMPLES {
+        let mut req = [0_u8; NTP_PACKET_LEN];
+        // li 0, version 4, mode 3 client
+        req[0] = 0x23;
+        let t0 = now_ms();
+        if socket.send(&req).is_err() {
+            continue;
+        }
+        let mut buf = [0_u8; NTP_PACKET_LEN];
+        let n = match socket.recv(&mut buf) {
+            Ok(n) => n,
+            Err(_) => continue,
+        };
+        let t3 = now_ms();
+        if let Some((offset, delay)) = parse_sntp_response(&buf[..n], t0, t3) {
+            if best.map_or(true, |(_, best_delay)| delay < best_delay) {
+                best = Some((offset, delay));
+            }
+        }
+    }
+    best.map(|(offset, _)| offset)
+}
+
+pub fn start_ntp_offset_collect_t(server: String) {
+    thread::spawn(move || loop {
+        let offset = get_clock_offset_ms(&server);
+        match offset {
+            Some(offset) if offset.abs() > NTP_DRIFT_WARN_MS => {
+                warn!("clock drift {}ms against {}", offset, server);
+            }
+            Some(offset) => debug!("ntp offset => {}ms", offset),
+            None => warn!("ntp query {} failed", server),
+        }
+        if let Ok(mut o) = G_NTP_OFFSET_MS.lock() {
+            *o = offset;
+        }
+
+        thread::sleep(Duration::from_secs(NTP_INTERVAL_SECS));
+    });
+}
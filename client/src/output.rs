@@ -0,0 +1,85 @@
+// where a sampled report goes, the network reporters live in the bin,
+// library users plug in their own `Output`
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use stat_common::server_status::StatRequest;
+
+#[derive(Debug)]
+pub enum OutputError {
+    Io(io::Error),
+    Encode(serde_json::Error),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputError::Io(err) => write!(f, "output io => {}", err),
+            OutputError::Encode(err) => write!(f, "output encode => {}", err),
+        }
+    }
+}
+
+impl std::error::Error for OutputError {}
+
+impl From<io::Error> for OutputError {
+    fn from(err: io::Error) -> Self {
+        OutputError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for OutputError {
+    fn from(err: serde_json::Error) -> Self {
+        OutputError::Encode(err)
+    }
+}
+
+pub trait Output {
+    fn write(&mut self, stat: &StatRequest) -> Result<(), OutputError>;
+}
+
+fn write_json_line<W: Write>(w: &mut W, stat: &StatRequest) -> Result<(), OutputError> {
+    let mut line = serde_json::to_vec(stat)?;
+    line.push(b'\n');
+    w.write_all(&line)?;
+    w.flush()?;
+    Ok(())
+}
+
+// one json object per line
+pub struct StdoutOutput;
+
+impl Output for StdoutOutput {
+    fn write(&mut self, stat: &StatRequest) -> Result<(), OutputError> {
+        write_json_line(&mut io::stdout().lock(), stat)
+    }
+}
+
+// appends json lines, the file is created when missing
+pub struct FileOutput {
+    file: File,
+}
+
+impl FileOutput {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(FileOutput { file })
+    }
+}
+
+impl Output for FileOutput {
+    fn write(&mut self, stat: &StatRequest) -> Result<(), OutputError> {
+        write_json_line(&mut self.file, stat)
+    }
+}
+
+// drops every report, for benchmarking the collectors
+pub struct NullOutput;
+
+impl Output for NullOutput {
+    fn write(&mut self, _stat: &StatRequest) -> Result<(), OutputError> {
+        Ok(())
+    }
+}
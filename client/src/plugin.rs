@@ -0,0 +1,116 @@
+// plugin dir, every executable prints `key value` lines, merged into custom_metrics
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PLUGIN_INTERVAL_MS: u64 = 1000;
+
+lazy_static! {
+    pub static ref G_PLUGIN_METRICS: Arc<Mutex<HashMap<String, String>>> =
+        Arc::new(Default::default());
+}
+
+// `key value`, blank lines and `#` comments skipped
+pub fn parse_plugin_output(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            l.split_once(char::is_whitespace)
+                .map(|(k, v)| (k.to_string(), v.trim().to_string()))
+        })
+        .collect()
+}
+
+fn plugin_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| {
+                    entry
+                        .metadata()
+                        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+                        .unwrap_or(false)
+                })
+                .map(|entry| entry.path())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn run_plugin(path: &Path, timeout: Duration) -> Option<String> {
+    let mut child = Command::new(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| error!("plugin {:?} => {:?}", path, err))
+        .ok()?;
+
+    // drain stdout aside, a chatty plugin must not block on a full pipe
+    let mut stdout = child.stdout.take()?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stdout.read_to_string(&mut buf);
+        let _ = tx.send(buf);
+    });
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break,
+            Ok(Some(status)) => {
+                warn!("plugin {:?} exit => {}", path, status);
+                return None;
+            }
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            Ok(None) => {
+                warn!("plugin {:?} timeout after {:?}, killed", path, timeout);
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            Err(err) => {
+                error!("plugin {:?} => {:?}", path, err);
+                return None;
+            }
+        }
+    }
+
+    let grace = Duration::from_millis(100);
+    rx.recv_timeout(
+        deadline
+            .saturating_duration_since(Instant::now())
+            .max(grace),
+    )
+    .ok()
+}
+
+pub fn start_plugin_collect_t(dir: PathBuf, timeout: Duration) {
+    thread::spawn(move || loop {
+        let mut metrics = HashMap::new();
+        for path in plugin_files(&dir) {
+            if let Some(output) = run_plugin(&path, timeout) {
+                metrics.extend(parse_plugin_output(&output));
+            }
+        }
+        if let Ok(mut o) = G_PLUGIN_METRICS.lock() {
+            *o = metrics;
+        }
+
+        thread::sleep(Duration::from_millis(PLUGIN_INTERVAL_MS));
+    });
+}
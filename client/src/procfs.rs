@@ -0,0 +1,944 @@
+#![deny(warnings)]
+use lazy_static::lazy_static;
+use once_cell::sync::OnceCell;
+use regex::Regex;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+
+pub static IFACE_IGNORE_VEC: &[&str] = &["lo", "docker", "vnet", "veth", "vmbr", "kube", "br-"];
+
+// name is relative to the proc root, eg: `net/dev`
+pub trait ProcFs {
+    fn read_file(&self, name: &str) -> io::Result<String>;
+    // numeric entries of the proc root
+    fn list_pids(&self) -> io::Result<Vec<u32>>;
+
+    fn read_uptime(&self) -> io::Result<String> {
+        self.read_file("uptime")
+    }
+    fn read_loadavg(&self) -> io::Result<String> {
+        self.read_file("loadavg")
+    }
+    fn read_meminfo(&self) -> io::Result<String> {
+        self.read_file("meminfo")
+    }
+    fn read_net_dev(&self) -> io::Result<String> {
+        self.read_file("net/dev")
+    }
+    fn read_stat(&self) -> io::Result<String> {
+        self.read_file("stat")
+    }
+    fn read_mounts(&self) -> io::Result<String> {
+        self.read_file("mounts")
+    }
+}
+
+static PROC_ROOT: OnceCell<PathBuf> = OnceCell::new();
+
+// set once at startup, before any collector runs, eg: a host /proc bind mounted into a container
+pub fn set_proc_root<P: AsRef<Path>>(root: P) {
+    let _ = PROC_ROOT.set(root.as_ref().to_path_buf());
+}
+
+pub fn proc_path(name: &str) -> PathBuf {
+    PROC_ROOT
+        .get()
+        .map_or_else(|| Path::new("/proc"), |root| root.as_path())
+        .join(name)
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RealProcFs;
+
+impl ProcFs for RealProcFs {
+    fn read_file(&self, name: &str) -> io::Result<String> {
+        fs::read_to_string(proc_path(name))
+    }
+
+    fn list_pids(&self) -> io::Result<Vec<u32>> {
+        Ok(fs::read_dir(proc_path(""))?
+            .flatten()
+            .filter_map(|entry| entry.file_name().into_string().ok()?.parse::<u32>().ok())
+            .collect())
+    }
+}
+
+// keeps the fd open and re-reads from offset 0 with pread,
+// saves the open/close pair per sample on the hot collector threads
+#[derive(Debug)]
+pub struct ProcFile {
+    file: fs::File,
+    buf: Vec<u8>,
+}
+
+impl ProcFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self {
+            file: fs::File::open(path)?,
+            buf: vec![0; 4096],
+        })
+    }
+
+    pub fn read(&mut self) -> io::Result<&str> {
+        let mut len = 0;
+        loop {
+            if len == self.buf.len() {
+                self.buf.resize(len * 2, 0);
+            }
+            match read_at(&self.file, &mut self.buf[len..], len as u64)? {
+                0 => break,
+                n => len += n,
+            }
+        }
+        std::str::from_utf8(&self.buf[..len])
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+#[cfg(unix)]
+fn read_at(file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::os::unix::fs::FileExt;
+    file.read_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::os::windows::fs::FileExt;
+    file.seek_read(buf, offset)
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MockProcFs<'a>(pub HashMap<&'a str, &'a str>);
+
+impl ProcFs for MockProcFs<'_> {
+    fn read_file(&self, name: &str) -> io::Result<String> {
+        self.0
+            .get(name)
+            .map(|s| s.to_string())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, name.to_string()))
+    }
+
+    // pids of the `<pid>/...` keys
+    fn list_pids(&self) -> io::Result<Vec<u32>> {
+        let mut pids = self
+            .0
+            .keys()
+            .filter_map(|name| name.split('/').next()?.parse::<u32>().ok())
+            .collect::<Vec<_>>();
+        pids.sort_unstable();
+        pids.dedup();
+        Ok(pids)
+    }
+}
+
+// /proc/loadavg
+pub fn get_loadavg_from_reader<R: BufRead>(mut reader: R) -> (f64, f64, f64) {
+    let mut buf = String::new();
+    if reader.read_line(&mut buf).is_err() {
+        return (0.0, 0.0, 0.0);
+    }
+    let a = buf
+        .split_whitespace()
+        .take(3)
+        .filter_map(|v| v.parse::<f64>().ok())
+        .collect::<Vec<f64>>();
+    if a.len() == 3 {
+        return (a[0], a[1], a[2]);
+    }
+    (0.0, 0.0, 0.0)
+}
+
+// /proc/meminfo
+// the unit is missing on counts, eg: `HugePages_Total:       0`
+static MEMORY_REGEX: &str = r#"^(?P<key>\S*):\s*(?P<value>\d*)(\s*kB)?"#;
+lazy_static! {
+    static ref MEMORY_REGEX_RE: Regex = Regex::new(MEMORY_REGEX).unwrap();
+}
+pub fn get_memory_from_reader<R: BufRead>(reader: R) -> (u64, u64, u64, u64) {
+    let mut res_dict = HashMap::new();
+    for l in reader.lines().map_while(Result::ok) {
+        if let Some(caps) = MEMORY_REGEX_RE.captures(&l) {
+            if let Ok(v) = caps["value"].parse::<u64>() {
+                res_dict.insert(caps["key"].to_string(), v);
+            }
+        };
+    }
+    let get = |k: &str| res_dict.get(k).copied().unwrap_or(0);
+
+    let mem_total = get("MemTotal");
+    let swap_total = get("SwapTotal");
+    let swap_free = get("SwapFree");
+
+    // the hugetlb pool is carved out of MemTotal up front and never shows in MemFree,
+    // free or not, so it would read as used, reported apart as memory_hugepages_kb
+    // https://www.kernel.org/doc/html/latest/admin-guide/mm/hugetlbpage.html
+    let hugepages = get("HugePages_Total") * get("Hugepagesize");
+
+    let mem_used = mem_total
+        .saturating_sub(get("MemFree"))
+        .saturating_sub(get("Buffers"))
+        .saturating_sub(get("Cached"))
+        .saturating_sub(get("SReclaimable"))
+        .saturating_sub(hugepages);
+
+    (mem_total, mem_used, swap_total, swap_free)
+}
+
+// /proc/meminfo, (Dirty, Writeback)
+pub fn get_memory_dirty_from_reader<R: BufRead>(reader: R) -> (u64, u64) {
+    let (mut dirty, mut writeback) = (0, 0);
+    for l in reader.lines().map_while(Result::ok) {
+        if let Some(caps) = MEMORY_REGEX_RE.captures(&l) {
+            match &caps["key"] {
+                "Dirty" => dirty = caps["value"].parse::<u64>().unwrap_or(0),
+                "Writeback" => writeback = caps["value"].parse::<u64>().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+    (dirty, writeback)
+}
+
+// /proc/meminfo, (Committed_AS, CommitLimit)
+pub fn get_memory_commit_from_reader<R: BufRead>(reader: R) -> (u64, u64) {
+    let (mut committed, mut limit) = (0, 0);
+    for l in reader.lines().map_while(Result::ok) {
+        if let Some(caps) = MEMORY_REGEX_RE.captures(&l) {
+            match &caps["key"] {
+                "Committed_AS" => committed = caps["value"].parse::<u64>().unwrap_or(0),
+                "CommitLimit" => limit = caps["value"].parse::<u64>().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+    (committed, limit)
+}
+
+// /proc/meminfo, HugePages_Total * Hugepagesize KiB, the default size hugetlb pool
+pub fn get_hugepages_from_reader<R: BufRead>(reader: R) -> u64 {
+    let (mut total, mut size) = (0, 0);
+    for l in reader.lines().map_while(Result::ok) {
+        if let Some(caps) = MEMORY_REGEX_RE.captures(&l) {
+            match &caps["key"] {
+                "HugePages_Total" => total = caps["value"].parse::<u64>().unwrap_or(0),
+                "Hugepagesize" => size = caps["value"].parse::<u64>().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+    total * size
+}
+
+// /proc/meminfo, AnonHugePages, thp backed anonymous memory
+pub fn get_anon_huge_pages_from_reader<R: BufRead>(reader: R) -> u64 {
+    for l in reader.lines().map_while(Result::ok) {
+        if let Some(caps) = MEMORY_REGEX_RE.captures(&l) {
+            if &caps["key"] == "AnonHugePages" {
+                return caps["value"].parse::<u64>().unwrap_or(0);
+            }
+        }
+    }
+    0
+}
+
+// sysfs multiple choice files mark the active one with brackets
+fn bracketed_choice(contents: &str) -> Option<String> {
+    contents
+        .split_whitespace()
+        .find_map(|w| w.strip_prefix('[')?.strip_suffix(']'))
+        .map(|choice| choice.to_string())
+}
+
+// /sys/kernel/mm/transparent_hugepage/enabled, the bracketed one, eg: `always [madvise] never`
+pub fn get_thp_mode(contents: &str) -> Option<String> {
+    bracketed_choice(contents)
+}
+
+// /sys/block/<dev>/queue/scheduler, the bracketed one, eg: `[mq-deadline] kyber bfq none`,
+// a lone unbracketed `none` when the queue offers no choice
+pub fn get_block_scheduler(contents: &str) -> Option<String> {
+    bracketed_choice(contents).or_else(|| {
+        let mut it = contents.split_whitespace();
+        match (it.next(), it.next()) {
+            (Some(only), None) => Some(only.to_string()),
+            _ => None,
+        }
+    })
+}
+
+// /sys/block/<dev>/inflight, `reads writes` requests in flight, summed
+pub fn get_block_inflight(contents: &str) -> Option<u32> {
+    let mut it = contents.split_whitespace();
+    let reads = it.next()?.parse::<u32>().ok()?;
+    let writes = it.next()?.parse::<u32>().ok()?;
+    Some(reads.saturating_add(writes))
+}
+
+// /sys/devices/system/node/nodeN/meminfo, (MemTotal, MemFree)
+// eg: `Node 0 MemTotal:       16318548 kB`
+pub fn get_numa_meminfo_from_reader<R: BufRead>(reader: R) -> (u64, u64) {
+    let (mut total, mut free) = (0, 0);
+    for l in reader.lines().map_while(Result::ok) {
+        let vec = l.split_whitespace().collect::<Vec<_>>();
+        if vec.len() < 4 {
+            continue;
+        }
+        match vec[2] {
+            "MemTotal:" => total = vec[3].parse::<u64>().unwrap_or(0),
+            "MemFree:" => free = vec[3].parse::<u64>().unwrap_or(0),
+            _ => {}
+        }
+    }
+    (total, free)
+}
+
+// /proc/cpuinfo, (model name, physical cores, logical cores)
+// physical cores are distinct (physical id, core id) pairs, logical if those are absent
+pub fn get_cpuinfo_from_reader<R: BufRead>(reader: R) -> (String, u32, u32) {
+    let (mut model, mut logical) = (String::new(), 0_u32);
+    let mut cores = std::collections::HashSet::new();
+    let (mut physical_id, mut core_id) = (None, None);
+    for l in reader.lines().map_while(Result::ok) {
+        let (k, v) = match l.split_once(':') {
+            Some((k, v)) => (k.trim(), v.trim()),
+            None => continue,
+        };
+        match k {
+            "processor" => {
+                logical += 1;
+                physical_id = None;
+                core_id = None;
+            }
+            "model name" if model.is_empty() => model = v.to_string(),
+            "physical id" => physical_id = Some(v.to_string()),
+            "core id" => core_id = Some(v.to_string()),
+            _ => {}
+        }
+        if let (Some(p), Some(c)) = (&physical_id, &core_id) {
+            cores.insert((p.to_string(), c.to_string()));
+        }
+    }
+    let physical = if cores.is_empty() {
+        logical
+    } else {
+        cores.len() as u32
+    };
+    (model, physical, logical)
+}
+
+// /proc/self/status, (VmRSS, VmSize)
+pub fn get_self_memory_from_reader<R: BufRead>(reader: R) -> (u64, u64) {
+    let (mut rss, mut vms) = (0, 0);
+    for l in reader.lines().map_while(Result::ok) {
+        if let Some(caps) = MEMORY_REGEX_RE.captures(&l) {
+            match &caps["key"] {
+                "VmRSS" => rss = caps["value"].parse::<u64>().unwrap_or(0),
+                "VmSize" => vms = caps["value"].parse::<u64>().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+    (rss, vms)
+}
+
+// /proc/net/dev
+static TRAFFIC_REGEX: &str = r#"([^\s]+):[\s]{0,}(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)"#;
+lazy_static! {
+    static ref TRAFFIC_REGEX_RE: Regex = Regex::new(TRAFFIC_REGEX).unwrap();
+}
+pub fn get_sys_traffic_from_reader<R: BufRead>(reader: R) -> (u64, u64) {
+    let (mut network_in, mut network_out) = (0_u64, 0_u64);
+    for l in reader.lines().map_while(Result::ok) {
+        if let Some(caps) = TRAFFIC_REGEX_RE.captures(&l) {
+            let name = &caps[1];
+            if IFACE_IGNORE_VEC.iter().any(|sk| name.contains(*sk)) {
+                continue;
+            }
+            if let (Ok(net_in), Ok(net_out)) = (caps[2].parse::<u64>(), caps[10].parse::<u64>()) {
+                network_in = network_in.saturating_add(net_in);
+                network_out = network_out.saturating_add(net_out);
+            }
+        }
+    }
+
+    (network_in, network_out)
+}
+
+// /proc/net/dev, (rx packets, tx packets)
+pub fn get_sys_packets_from_reader<R: BufRead>(reader: R) -> (u64, u64) {
+    let (mut rx_packets, mut tx_packets) = (0_u64, 0_u64);
+    for l in reader.lines().map_while(Result::ok) {
+        if let Some(caps) = TRAFFIC_REGEX_RE.captures(&l) {
+            let name = &caps[1];
+            if IFACE_IGNORE_VEC.iter().any(|sk| name.contains(*sk)) {
+                continue;
+            }
+            if let (Ok(rx), Ok(tx)) = (caps[3].parse::<u64>(), caps[11].parse::<u64>()) {
+                rx_packets = rx_packets.saturating_add(rx);
+                tx_packets = tx_packets.saturating_add(tx);
+            }
+        }
+    }
+
+    (rx_packets, tx_packets)
+}
+
+// /proc/net/dev, iface => (rx bytes, tx bytes)
+pub fn get_iface_traffic_from_reader<R: BufRead>(reader: R) -> HashMap<String, (u64, u64)> {
+    let mut res_dict = HashMap::new();
+    for l in reader.lines().map_while(Result::ok) {
+        if let Some(caps) = TRAFFIC_REGEX_RE.captures(&l) {
+            let name = &caps[1];
+            if IFACE_IGNORE_VEC.iter().any(|sk| name.contains(*sk)) {
+                continue;
+            }
+            if let (Ok(net_in), Ok(net_out)) = (caps[2].parse::<u64>(), caps[10].parse::<u64>()) {
+                res_dict.insert(name.to_string(), (net_in, net_out));
+            }
+        }
+    }
+    res_dict
+}
+
+// /proc/net/dev, (rx_errs, tx_errs, rx_drop, tx_drop)
+pub fn get_net_errors_from_reader<R: BufRead>(reader: R) -> (u64, u64, u64, u64) {
+    let (mut rx_errs, mut tx_errs, mut rx_drop, mut tx_drop) = (0_u64, 0_u64, 0_u64, 0_u64);
+    for l in reader.lines().map_while(Result::ok) {
+        let (name, counters) = match l.split_once(':') {
+            Some((name, counters)) => (name.trim(), counters),
+            None => continue,
+        };
+        if IFACE_IGNORE_VEC.iter().any(|sk| name.contains(*sk)) {
+            continue;
+        }
+        let v = counters
+            .split_whitespace()
+            .map(|s| s.parse::<u64>().unwrap_or(0))
+            .collect::<Vec<_>>();
+        if v.len() < 16 {
+            continue;
+        }
+        rx_errs = rx_errs.saturating_add(v[2]);
+        rx_drop = rx_drop.saturating_add(v[3]);
+        tx_errs = tx_errs.saturating_add(v[10]);
+        tx_drop = tx_drop.saturating_add(v[11]);
+    }
+
+    (rx_errs, tx_errs, rx_drop, tx_drop)
+}
+
+// /proc/net/softnet_stat, one hex row per cpu
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SoftnetStats {
+    pub total_dropped: u64,
+    pub total_time_squeeze: u64,
+    pub total_cpu_collision: u64,
+}
+
+pub fn get_softnet_stats_from_reader<R: BufRead>(reader: R) -> SoftnetStats {
+    let mut stats = SoftnetStats::default();
+    for l in reader.lines().map_while(Result::ok) {
+        let v = l
+            .split_whitespace()
+            .map(|s| u64::from_str_radix(s, 16).unwrap_or(0))
+            .collect::<Vec<_>>();
+        if v.len() < 9 {
+            continue;
+        }
+        stats.total_dropped = stats.total_dropped.saturating_add(v[1]);
+        stats.total_time_squeeze = stats.total_time_squeeze.saturating_add(v[2]);
+        stats.total_cpu_collision = stats.total_cpu_collision.saturating_add(v[8]);
+    }
+    stats
+}
+
+// /proc/diskstats
+#[derive(Debug, Default, Clone)]
+pub struct DiskStat {
+    pub reads: u64,
+    pub read_ms: u64,
+    pub writes: u64,
+    pub write_ms: u64,
+    pub io_ticks: u64,
+}
+
+pub fn get_diskstats_from_reader<R: BufRead>(reader: R) -> HashMap<String, DiskStat> {
+    let mut res_dict = HashMap::new();
+    for l in reader.lines().map_while(Result::ok) {
+        let vec = l.split_whitespace().collect::<Vec<_>>();
+        if vec.len() < 13 {
+            continue;
+        }
+        let v = vec[3..13]
+            .iter()
+            .map(|s| s.parse::<u64>().unwrap_or(0))
+            .collect::<Vec<_>>();
+        res_dict.insert(
+            vec[2].to_string(),
+            DiskStat {
+                reads: v[0],
+                read_ms: v[3],
+                writes: v[4],
+                write_ms: v[7],
+                io_ticks: v[9],
+            },
+        );
+    }
+    res_dict
+}
+
+// `netstat -ib -n` on macos, only the <Link#N> row of each interface
+pub fn get_netstat_traffic_from_reader<R: BufRead>(reader: R) -> (u64, u64) {
+    let (mut network_in, mut network_out) = (0_u64, 0_u64);
+    for l in reader.lines().map_while(Result::ok) {
+        let vec = l.split_whitespace().collect::<Vec<_>>();
+        if vec.len() < 10 || !vec[2].starts_with("<Link#") {
+            continue;
+        }
+        let name = vec[0].trim_end_matches('*');
+        if IFACE_IGNORE_VEC.iter().any(|sk| name.contains(*sk)) {
+            continue;
+        }
+        // Ipkts Ierrs Ibytes Opkts Oerrs Obytes Coll
+        let n = vec.len();
+        if let (Ok(net_in), Ok(net_out)) = (vec[n - 5].parse::<u64>(), vec[n - 2].parse::<u64>()) {
+            network_in = network_in.saturating_add(net_in);
+            network_out = network_out.saturating_add(net_out);
+        }
+    }
+
+    (network_in, network_out)
+}
+
+// `df -Tlm --total`, (total, used) MiB of the `total` row, None if absent or garbled
+pub fn get_df_total_from_reader<R: BufRead>(reader: R) -> Option<(u64, u64)> {
+    let last = reader
+        .lines()
+        .map_while(Result::ok)
+        .filter(|l| !l.trim().is_empty())
+        .last()?;
+    let vec = last.split_whitespace().collect::<Vec<_>>();
+    if vec.len() < 4 || vec[0] != "total" {
+        return None;
+    }
+    Some((vec[2].parse::<u64>().ok()?, vec[3].parse::<u64>().ok()?))
+}
+
+// `nft -j list ruleset`, bytes of the counter on the rule matching table/chain/comment
+pub fn get_nft_counter_bytes(json: &str, table: &str, chain: &str, comment: &str) -> Option<u64> {
+    let v: serde_json::Value = serde_json::from_str(json).ok()?;
+    v.get("nftables")?
+        .as_array()?
+        .iter()
+        .filter_map(|item| item.get("rule"))
+        .find(|rule| {
+            rule.get("table").and_then(|s| s.as_str()) == Some(table)
+                && rule.get("chain").and_then(|s| s.as_str()) == Some(chain)
+                && rule.get("comment").and_then(|s| s.as_str()) == Some(comment)
+        })?
+        .get("expr")?
+        .as_array()?
+        .iter()
+        .find_map(|expr| expr.get("counter")?.get("bytes")?.as_u64())
+}
+
+// `iptables -nvx -L <chain> -t <table>`, bytes of the rule with `/* comment */`
+pub fn get_iptables_counter_bytes_from_reader<R: BufRead>(reader: R, comment: &str) -> Option<u64> {
+    let tag = format!("/* {} */", comment);
+    reader
+        .lines()
+        .map_while(Result::ok)
+        .find(|l| l.contains(&tag))
+        .and_then(|l| l.split_whitespace().nth(1)?.parse::<u64>().ok())
+}
+
+// dmesg, watchdog `soft lockup` / `hard LOCKUP` lines
+pub fn get_lockup_count_from_reader<R: BufRead>(reader: R) -> u64 {
+    reader
+        .lines()
+        .map_while(Result::ok)
+        .filter(|l| l.contains("soft lockup") || l.contains("hard LOCKUP"))
+        .count() as u64
+}
+
+pub const KERNEL_ERROR_LINE_MAX: usize = 200;
+
+// dmesg, the last n lines mentioning error / fail / bug in any case, bug as a whole word
+// so debugfs noise stays out, oldest first, each cut to KERNEL_ERROR_LINE_MAX bytes
+pub fn get_kernel_errors_from_reader<R: BufRead>(reader: R, n: usize) -> Vec<String> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut errors = VecDeque::with_capacity(n);
+    for l in reader.lines().map_while(Result::ok) {
+        let lower = l.to_lowercase();
+        let is_bug = || {
+            lower
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .any(|w| w == "bug")
+        };
+        if !(lower.contains("error") || lower.contains("fail") || is_bug()) {
+            continue;
+        }
+        if errors.len() == n {
+            errors.pop_front();
+        }
+        errors.push_back(truncate_str(l.trim_end(), KERNEL_ERROR_LINE_MAX).to_string());
+    }
+    errors.into()
+}
+
+fn truncate_str(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+// `throttled=0x50005` from vcgencmd, or the bare hex of the firmware sysfs file
+pub fn get_throttled_from_str(s: &str) -> Option<u32> {
+    let s = s.trim();
+    let s = s.strip_prefix("throttled=").unwrap_or(s);
+    u32::from_str_radix(s.trim_start_matches("0x"), 16).ok()
+}
+
+// low bits are the current state, bits 16.. the same reasons seen since boot
+pub fn get_throttle_reasons(mask: u32) -> Vec<String> {
+    [
+        (0, "under-voltage"),
+        (1, "freq-capped"),
+        (2, "throttled"),
+        (3, "soft-temp-limit"),
+    ]
+    .iter()
+    .filter(|(bit, _)| mask & (1 << bit) != 0)
+    .map(|(_, reason)| reason.to_string())
+    .collect()
+}
+
+pub const PF_KTHREAD: u32 = 0x0020_0000;
+
+// /proc/<pid>/stat, field 9 flags, comm may hold spaces and parens so split after the last `)`
+pub fn get_pid_stat_flags(contents: &str) -> Option<u32> {
+    let (_, rest) = contents.rsplit_once(')')?;
+    rest.split_whitespace().nth(6)?.parse::<u32>().ok()
+}
+
+// /proc/<pid>/stat, (comm, utime + stime ticks), fields 14 and 15
+pub fn get_pid_stat_times(contents: &str) -> Option<(String, u64)> {
+    let (head, rest) = contents.rsplit_once(')')?;
+    let (_, comm) = head.split_once('(')?;
+    let mut it = rest.split_whitespace().skip(11);
+    let utime = it.next()?.parse::<u64>().ok()?;
+    let stime = it.next()?.parse::<u64>().ok()?;
+    Some((comm.to_string(), utime + stime))
+}
+
+// /proc/stat, (sum of the aggregate cpu line, cpuN line count)
+pub fn get_stat_cpu_total_from_reader<R: BufRead>(reader: R) -> (u64, u32) {
+    let (mut total, mut cpus) = (0, 0);
+    for l in reader.lines().map_while(Result::ok) {
+        if l.starts_with("cpu ") {
+            total = l
+                .split_whitespace()
+                .skip(1)
+                .take(8)
+                .map(|s| s.parse::<u64>().unwrap_or(0))
+                .sum();
+        } else if l.starts_with("cpu") {
+            cpus += 1;
+        }
+    }
+    (total, cpus)
+}
+
+// /proc/stat, (intr total, softirq total)
+pub fn get_stat_irq_from_reader<R: BufRead>(reader: R) -> (u64, u64) {
+    let (mut intr, mut softirq) = (0, 0);
+    for l in reader.lines().map_while(Result::ok) {
+        let mut iter = l.split_whitespace();
+        match (iter.next(), iter.next().map(|v| v.parse::<u64>())) {
+            (Some("intr"), Some(Ok(v))) => intr = v,
+            (Some("softirq"), Some(Ok(v))) => softirq = v,
+            _ => {}
+        }
+    }
+    (intr, softirq)
+}
+
+// 64 hex chars, bare or wrapped, eg: docker-<id>.scope, cri-containerd-<id>.scope
+fn container_id_in(segment: &str) -> Option<&str> {
+    let id = segment.trim_end_matches(".scope").rsplit('-').next()?;
+    (id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit())).then(|| id)
+}
+
+fn container_runtime_of(path: &str) -> Option<&'static str> {
+    if path.contains("libpod") || path.contains("overlay-containers") {
+        Some("podman")
+    } else if path.contains("crio") {
+        Some("cri-o")
+    } else if path.contains("containerd") {
+        Some("containerd")
+    } else if path.contains("docker") {
+        Some("docker")
+    } else if path.contains("kubepods") {
+        Some("kubernetes")
+    } else {
+        None
+    }
+}
+
+// /proc/self/cgroup, (runtime, container id), None on bare metal or a private cgroupns
+pub fn get_container_from_cgroup_reader<R: BufRead>(reader: R) -> Option<(&'static str, String)> {
+    reader.lines().map_while(Result::ok).find_map(|l| {
+        let path = l.splitn(3, ':').nth(2)?;
+        let id = path.split('/').find_map(container_id_in)?;
+        Some((container_runtime_of(path)?, id.to_string()))
+    })
+}
+
+// /proc/self/mountinfo, the bind mounted /etc/hostname etc. of docker and podman,
+// eg: /var/lib/docker/containers/<id>/hostname
+// overlay layer dirs are 64 hex too, only ids right under a containers dir count
+pub fn get_container_from_mountinfo_reader<R: BufRead>(
+    reader: R,
+) -> Option<(&'static str, String)> {
+    reader.lines().map_while(Result::ok).find_map(|l| {
+        let root = l.split_whitespace().nth(3)?;
+        let segments = root.split('/').collect::<Vec<_>>();
+        segments.windows(2).find_map(|w| match w[0] {
+            "containers" | "overlay-containers" => {
+                let id = container_id_in(w[1])?;
+                Some((container_runtime_of(root)?, id.to_string()))
+            }
+            _ => None,
+        })
+    })
+}
+
+// /proc/stat, `btime 1700000000`, boot time as a unix timestamp
+pub fn get_btime_from_reader<R: BufRead>(reader: R) -> Option<u64> {
+    reader.lines().map_while(Result::ok).find_map(|l| {
+        l.strip_prefix("btime ")
+            .and_then(|v| v.trim().parse::<u64>().ok())
+    })
+}
+
+// /proc/stat, (procs_running, procs_blocked), the tasks loadavg counts
+pub fn get_procs_running_from_reader<R: BufRead>(reader: R) -> Option<(u64, u64)> {
+    let (mut running, mut blocked) = (None, None);
+    for l in reader.lines().map_while(Result::ok) {
+        if let Some(v) = l.strip_prefix("procs_running ") {
+            running = v.trim().parse::<u64>().ok();
+        } else if let Some(v) = l.strip_prefix("procs_blocked ") {
+            blocked = v.trim().parse::<u64>().ok();
+        }
+    }
+    Some((running?, blocked?))
+}
+
+// /proc/stat softirq line, (total, NET_TX, NET_RX) raised since boot
+// softirq total HI TIMER NET_TX NET_RX BLOCK ...
+pub fn get_softirq_net_from_reader<R: BufRead>(reader: R) -> Option<(u64, u64, u64)> {
+    let l = reader
+        .lines()
+        .map_while(Result::ok)
+        .find(|l| l.starts_with("softirq "))?;
+    let vec = l
+        .split_whitespace()
+        .skip(1)
+        .map(|v| v.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    Some((*vec.first()?, *vec.get(3)?, *vec.get(4)?))
+}
+
+// /proc/mounts
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MountEntry {
+    pub device: String,
+    pub mount_point: String,
+    pub fs_type: String,
+    pub options: Vec<String>,
+}
+
+// spaces etc. are escaped as octal, eg: `\040`
+fn unescape_mount_field(s: &str) -> String {
+    let b = s.as_bytes();
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b'\\'
+            && i + 3 < b.len()
+            && b[i + 1..i + 4].iter().all(|c| (b'0'..=b'7').contains(c))
+        {
+            let v = b[i + 1..i + 4]
+                .iter()
+                .fold(0_u32, |acc, c| acc * 8 + (c - b'0') as u32);
+            out.push(v as u8);
+            i += 4;
+            continue;
+        }
+        out.push(b[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+pub fn get_mounts_from_reader<R: BufRead>(reader: R) -> Vec<MountEntry> {
+    let mut mounts = Vec::new();
+    for l in reader.lines().map_while(Result::ok) {
+        let vec = l.split_whitespace().collect::<Vec<_>>();
+        if vec.len() < 4 {
+            continue;
+        }
+        mounts.push(MountEntry {
+            device: unescape_mount_field(vec[0]),
+            mount_point: unescape_mount_field(vec[1]),
+            fs_type: vec[2].to_string(),
+            options: vec[3].split(',').map(|s| s.to_string()).collect(),
+        });
+    }
+    mounts
+}
+
+// /proc/swaps, KiB
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SwapEntry {
+    pub name: String,
+    pub swap_type: String,
+    pub size_kb: u64,
+    pub used_kb: u64,
+    pub priority: i32,
+}
+
+pub fn get_swaps_from_reader<R: BufRead>(reader: R) -> Vec<SwapEntry> {
+    let mut swaps = Vec::new();
+    // skip the `Filename Type Size Used Priority` header
+    for l in reader.lines().map_while(Result::ok).skip(1) {
+        let vec = l.split_whitespace().collect::<Vec<_>>();
+        if vec.len() < 5 {
+            continue;
+        }
+        swaps.push(SwapEntry {
+            name: unescape_mount_field(vec[0]),
+            swap_type: vec[1].to_string(),
+            size_kb: vec[2].parse::<u64>().unwrap_or(0),
+            used_kb: vec[3].parse::<u64>().unwrap_or(0),
+            priority: vec[4].parse::<i32>().unwrap_or(0),
+        });
+    }
+    swaps
+}
+
+// /sys/block/zramN/mm_stat, (orig_data_size, compr_data_size) bytes
+pub fn get_zram_mm_stat(contents: &str) -> Option<(u64, u64)> {
+    let mut it = contents.split_whitespace();
+    let orig = it.next()?.parse::<u64>().ok()?;
+    let compr = it.next()?.parse::<u64>().ok()?;
+    Some((orig, compr))
+}
+
+// /proc/net/tcp{,6}, LISTEN (0A) rows, (local port, rx_queue aka accept backlog)
+pub fn get_tcp_listen_backlog_from_reader<R: BufRead>(reader: R) -> Vec<(u16, u32)> {
+    let mut res = Vec::new();
+    for l in reader.lines().map_while(Result::ok) {
+        let vec = l.split_whitespace().collect::<Vec<_>>();
+        if vec.len() < 5 || vec[3] != "0A" {
+            continue;
+        }
+        let port = vec[1]
+            .rsplit_once(':')
+            .and_then(|(_, port)| u16::from_str_radix(port, 16).ok());
+        let rx_queue = vec[4]
+            .split_once(':')
+            .and_then(|(_, rx)| u32::from_str_radix(rx, 16).ok());
+        if let (Some(port), Some(rx_queue)) = (port, rx_queue) {
+            res.push((port, rx_queue));
+        }
+    }
+    res
+}
+
+// /proc/net/tcp{,6} address hex, each 32 bit word printed in host byte order
+pub fn decode_proc_net_addr(hex: &str) -> Option<IpAddr> {
+    let word = |i: usize| {
+        hex.get(i * 8..i * 8 + 8)
+            .and_then(|w| u32::from_str_radix(w, 16).ok())
+            .map(u32::to_ne_bytes)
+    };
+    match hex.len() {
+        8 => Some(IpAddr::V4(Ipv4Addr::from(word(0)?))),
+        32 => {
+            let mut octets = [0_u8; 16];
+            for i in 0..4 {
+                octets[i * 4..i * 4 + 4].copy_from_slice(&word(i)?);
+            }
+            // v4 clients on a dual stack listener, ::ffff:a.b.c.d
+            Some(match octets {
+                [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, a, b, c, d] => {
+                    IpAddr::V4(Ipv4Addr::new(a, b, c, d))
+                }
+                _ => IpAddr::V6(Ipv6Addr::from(octets)),
+            })
+        }
+        _ => None,
+    }
+}
+
+// /proc/net/tcp{,6}, ESTABLISHED (01) rows, remote address, loopback skipped
+pub fn get_tcp_remote_addrs_from_reader<R: BufRead>(reader: R) -> Vec<IpAddr> {
+    let mut res = Vec::new();
+    for l in reader.lines().map_while(Result::ok) {
+        let vec = l.split_whitespace().collect::<Vec<_>>();
+        if vec.len() < 4 || vec[3] != "01" {
+            continue;
+        }
+        let addr = vec[2]
+            .rsplit_once(':')
+            .and_then(|(addr, _)| decode_proc_net_addr(addr));
+        if let Some(addr) = addr {
+            if !addr.is_loopback() {
+                res.push(addr);
+            }
+        }
+    }
+    res
+}
+
+// counter delta, rollover safe
+pub fn wrapping_diff(cur: u64, pre: u64) -> u64 {
+    cur.wrapping_sub(pre)
+}
+
+// bytes/s
+pub fn calc_speed(cur: u64, pre: u64, diff: f64) -> u64 {
+    if diff <= 0.0 {
+        return 0;
+    }
+    (wrapping_diff(cur, pre) as f64 / diff) as u64
+}
+
+// /proc/spl/kstat/zfs/arcstats
+pub fn get_zfs_arcstats_from_reader<R: BufRead>(reader: R) -> (u64, u64) {
+    let (mut arc_size, mut arc_max) = (0, 0);
+    for l in reader.lines().map_while(Result::ok) {
+        let vec = l.split_whitespace().collect::<Vec<_>>();
+        if vec.len() != 3 {
+            continue;
+        }
+        match vec[0] {
+            "size" => arc_size = vec[2].parse::<u64>().unwrap_or(0),
+            "c_max" => arc_max = vec[2].parse::<u64>().unwrap_or(0),
+            _ => {}
+        }
+    }
+    (arc_size, arc_max)
+}
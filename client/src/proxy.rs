@@ -0,0 +1,182 @@
+// HTTP_PROXY/HTTPS_PROXY/NO_PROXY for the transports reqwest does not cover,
+// a plain http CONNECT tunnel, the target connects directly when no proxy applies
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::Url;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// proxy response headers larger than this are refused
+const CONNECT_RESP_MAX: usize = 8192;
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .or_else(|| std::env::var(name.to_lowercase()).ok())
+        .filter(|v| !v.trim().is_empty())
+}
+
+// NO_PROXY entries, eg: `localhost,.internal,10.0.0.1`, `*` bypasses every host,
+// an entry matches the host itself and all its subdomains, cidr is not supported
+pub fn no_proxy_matches(no_proxy: &str, host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    no_proxy
+        .split(',')
+        .map(|entry| {
+            entry
+                .trim()
+                .trim_start_matches("*.")
+                .trim_start_matches('.')
+        })
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            entry == "*"
+                || host.eq_ignore_ascii_case(entry)
+                || host
+                    .to_lowercase()
+                    .ends_with(&format!(".{}", entry.to_lowercase()))
+        })
+}
+
+// `host:port` without a scheme is taken as an http proxy
+pub fn parse_proxy_url(s: &str) -> Option<Url> {
+    let s = s.trim();
+    if s.contains("://") {
+        Url::parse(s).ok()
+    } else {
+        Url::parse(&format!("http://{}", s)).ok()
+    }
+}
+
+// --proxy value, Debug drops the user:pass@ part
+#[derive(Clone)]
+pub struct ProxyUrl(pub String);
+
+impl fmt::Debug for ProxyUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match parse_proxy_url(&self.0) {
+            Some(mut url) => {
+                if !url.username().is_empty() || url.password().is_some() {
+                    let _ = url.set_username("***");
+                    let _ = url.set_password(None);
+                }
+                write!(f, "{:?}", url.as_str())
+            }
+            // unparsable, may still carry credentials
+            None => f.write_str("***"),
+        }
+    }
+}
+
+impl std::str::FromStr for ProxyUrl {
+    type Err = std::convert::Infallible;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(ProxyUrl(s.to_string()))
+    }
+}
+
+// `get` looks up an env var, split out for the tests
+pub fn proxy_for_with<F>(get: F, host: &str, https: bool) -> Option<Url>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if get("NO_PROXY").map_or(false, |no_proxy| no_proxy_matches(&no_proxy, host)) {
+        return None;
+    }
+    let proxy = if https {
+        get("HTTPS_PROXY").or_else(|| get("HTTP_PROXY"))
+    } else {
+        get("HTTP_PROXY")
+    };
+    parse_proxy_url(&proxy?)
+}
+
+// the proxy from the env for `host`, None means connect directly
+pub fn env_proxy_for(host: &str, https: bool) -> Option<Url> {
+    proxy_for_with(env_var, host, https)
+}
+
+fn connect_request(proxy: &Url, target: &SocketAddr) -> String {
+    let mut req = format!(
+        "CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n",
+        target = target
+    );
+    if !proxy.username().is_empty() {
+        let credentials = format!(
+            "{}:{}",
+            proxy.username(),
+            proxy.password().unwrap_or_default()
+        );
+        req.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            STANDARD.encode(credentials)
+        ));
+    }
+    req.push_str("\r\n");
+    req
+}
+
+// status code of the response head, eg: `HTTP/1.1 200 Connection established`
+fn connect_status(head: &[u8]) -> Option<u16> {
+    let line = head.split(|&b| b == b'\r').next()?;
+    let line = std::str::from_utf8(line).ok()?;
+    let mut parts = line.split_whitespace();
+    if !parts.next()?.starts_with("HTTP/1.") {
+        return None;
+    }
+    parts.next()?.parse().ok()
+}
+
+// tunnel to `target` through an http proxy, the stream is ready for the target protocol
+pub async fn connect_via_proxy(proxy: &Url, target: &SocketAddr) -> io::Result<TcpStream> {
+    if proxy.scheme() != "http" {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} proxy, only http CONNECT is supported", proxy.scheme()),
+        ));
+    }
+    let proxy_addr = proxy
+        .socket_addrs(|| Some(8080))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "proxy addr not resolved"))?;
+    let mut stream = TcpStream::connect(proxy_addr).await?;
+    stream
+        .write_all(connect_request(proxy, target).as_bytes())
+        .await?;
+
+    // read byte by byte so nothing past the head is taken from the tunnel
+    let mut head = Vec::with_capacity(128);
+    let mut b = [0_u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= CONNECT_RESP_MAX {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "proxy response head too large",
+            ));
+        }
+        if stream.read(&mut b).await? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "proxy closed during CONNECT",
+            ));
+        }
+        head.push(b[0]);
+    }
+
+    match connect_status(&head) {
+        Some(200) => Ok(stream),
+        Some(code) => Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!("proxy CONNECT {} => {}", target, code),
+        )),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "malformed proxy response",
+        )),
+    }
+}
@@ -0,0 +1,175 @@
+// experimental, one long-lived uni stream per connection,
+// frames are u32 big endian length + payload, the first frame is the `user@_@pass` token,
+// then one protobuf StatRequest per frame, a lost connection is redialed and the token resent
+use prost::Message;
+use quinn::{ClientConfig, Connection, Endpoint, SendStream, TransportConfig};
+use std::fmt;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+use stat_common::server_status::StatRequest;
+
+use crate::health;
+use crate::report_interval;
+use crate::Args;
+use crate::ReportSchedule;
+use crate::INTERVAL_MS;
+
+const RECONNECT_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+pub async fn report(args: &Args, stat_base: &mut StatRequest) -> anyhow::Result<()> {
+    let endpoint_addr = args.quic_endpoint.as_ref().unwrap();
+    let host = endpoint_addr
+        .rsplit_once(':')
+        .map(|(host, _)| host.trim_start_matches('[').trim_end_matches(']'))
+        .unwrap_or(endpoint_addr)
+        .to_string();
+    let sock_addr = endpoint_addr.to_socket_addrs()?.next().unwrap();
+    stat_base.online4 = sock_addr.is_ipv4();
+    stat_base.online6 = sock_addr.is_ipv6();
+
+    let bind_addr: SocketAddr = if sock_addr.is_ipv6() {
+        "[::]:0".parse()?
+    } else {
+        "0.0.0.0:0".parse()?
+    };
+    let mut transport = TransportConfig::default();
+    transport.keep_alive_interval(Some(Duration::from_secs(5)));
+    let mut client_cfg = ClientConfig::with_native_roots();
+    client_cfg.transport = Arc::new(transport);
+
+    let mut endpoint = Endpoint::client(bind_addr)?;
+    endpoint.set_default_client_config(client_cfg);
+
+    let token = format!("{}@_@{}", args.user, args.pass.0);
+    let mut conn = ReportingConnection::new(endpoint, sock_addr, host, token);
+    let mut schedule = ReportSchedule::default();
+    loop {
+        // a paused loop still counts as alive
+        health::mark_cycle();
+        if args.skip_report() {
+            tokio::time::sleep(Duration::from_millis(INTERVAL_MS)).await;
+            continue;
+        }
+        let stat_rt = schedule.next(args, stat_base);
+        if let Err(err) = conn.send(&stat_rt).await {
+            error!("quic report error => {}", err);
+        }
+
+        tokio::time::sleep(report_interval(args)).await;
+    }
+}
+
+#[derive(Debug)]
+pub enum ConnectionError {
+    // the frame failed again on a fresh connection, dropped
+    Disconnected(anyhow::Error),
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionError::Disconnected(err) => write!(f, "disconnected => {:?}", err),
+        }
+    }
+}
+
+impl std::error::Error for ConnectionError {}
+
+// one uni stream, reconnected with backoff and re-authed whenever a write fails,
+// so a dropped connection costs the caller at most a late report
+pub struct ReportingConnection {
+    endpoint: Endpoint,
+    addr: SocketAddr,
+    host: String,
+    token: String,
+    stream: Option<(Connection, SendStream)>,
+    backoff: Duration,
+}
+
+impl ReportingConnection {
+    pub fn new(endpoint: Endpoint, addr: SocketAddr, host: String, token: String) -> Self {
+        ReportingConnection {
+            endpoint,
+            addr,
+            host,
+            token,
+            stream: None,
+            backoff: RECONNECT_BACKOFF_MIN,
+        }
+    }
+
+    pub async fn send(&mut self, stat: &StatRequest) -> Result<(), ConnectionError> {
+        let buf = stat.encode_to_vec();
+        if self.stream.is_some() {
+            match self.write(&buf).await {
+                Ok(_) => return Ok(()),
+                Err(err) => warn!("quic stream lost => {:?}, reconnecting", err),
+            }
+        }
+
+        self.reconnect().await;
+        self.write(&buf).await.map_err(|err| {
+            self.stream = None;
+            ConnectionError::Disconnected(err)
+        })
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> anyhow::Result<()> {
+        let (_, send) = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("not connected"))?;
+        write_frame(send, buf).await?;
+        debug!("quic report size => {}", buf.len());
+        Ok(())
+    }
+
+    // blocks until connected and authed, doubling the wait after each failure
+    async fn reconnect(&mut self) {
+        self.stream = None;
+        loop {
+            match self.handshake().await {
+                Ok(stream) => {
+                    self.stream = Some(stream);
+                    self.backoff = RECONNECT_BACKOFF_MIN;
+                    return;
+                }
+                Err(err) => {
+                    error!(
+                        "quic connect error => {:?}, retry in {:?}",
+                        err, self.backoff
+                    );
+                    tokio::time::sleep(self.backoff).await;
+                    self.backoff = (self.backoff * 2).min(RECONNECT_BACKOFF_MAX);
+                }
+            }
+        }
+    }
+
+    async fn handshake(&self) -> anyhow::Result<(Connection, SendStream)> {
+        let conn = connect(&self.endpoint, self.addr, &self.host).await?;
+        let mut send = conn.open_uni().await?;
+        write_frame(&mut send, self.token.as_bytes()).await?;
+        Ok((conn, send))
+    }
+}
+
+async fn connect(endpoint: &Endpoint, addr: SocketAddr, host: &str) -> anyhow::Result<Connection> {
+    let connecting = endpoint.connect(addr, host)?;
+    // 0-RTT once a session ticket of a previous connection is cached
+    let conn = match connecting.into_0rtt() {
+        Ok((new_conn, _)) => new_conn.connection,
+        Err(connecting) => connecting.await?.connection,
+    };
+    info!("quic connected => {}", conn.remote_address());
+    Ok(conn)
+}
+
+async fn write_frame(send: &mut SendStream, buf: &[u8]) -> anyhow::Result<()> {
+    send.write_all(&(buf.len() as u32).to_be_bytes()).await?;
+    send.write_all(buf).await?;
+    Ok(())
+}
@@ -1,336 +1,2177 @@
 // #![allow(unused)]
 use chrono::{Datelike, Local};
 use lazy_static::lazy_static;
-use regex::Regex;
+use once_cell::sync::{Lazy, OnceCell};
+#[cfg(unix)]
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::env;
+use std::ffi::OsStr;
 use std::fs;
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
+use std::io;
 use std::net::TcpStream;
-use std::net::{Shutdown, ToSocketAddrs};
+use std::net::{IpAddr, Shutdown, SocketAddr, ToSocketAddrs};
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::str;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::clock;
+use crate::hdd;
+#[cfg(unix)]
+use crate::plugin;
 use crate::Args;
+use stat_client::procfs;
+use stat_client::procfs::{ProcFs, RealProcFs, IFACE_IGNORE_VEC};
+use stat_client::units::KIB;
+use stat_common::server_status::DiskQueue;
+#[cfg(unix)]
+use stat_common::server_status::IfaceAddr;
+use stat_common::server_status::IfaceLink;
+use stat_common::server_status::NumaMemory;
+use stat_common::server_status::ProcInfo;
 use stat_common::server_status::StatRequest;
+use stat_common::server_status::SwapDevice;
+use stat_common::server_status::TopTalker;
 
 const SAMPLE_PERIOD: u64 = 1000; //ms
 const TIMEOUT_MS: u64 = 1000;
 static IPV4_ADDR: &str = "ipv4.google.com:80";
 static IPV6_ADDR: &str = "ipv6.google.com:80";
 
-pub fn get_uptime() -> u64 {
-    fs::read_to_string("/proc/uptime")
-        .map(|contents| {
-            if let Some(s) = contents.split('.').next() {
-                return s.parse::<u64>().unwrap_or(0);
+pub fn try_get_uptime<P: ProcFs>(fs: &P) -> io::Result<u64> {
+    let contents = fs.read_uptime()?;
+    contents
+        .split('.')
+        .next()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "uptime"))
+}
+
+pub fn get_uptime<P: ProcFs>(fs: &P) -> u64 {
+    try_get_uptime(fs).unwrap_or(0)
+}
+
+pub fn try_get_loadavg<P: ProcFs>(fs: &P) -> io::Result<(f64, f64, f64)> {
+    fs.read_loadavg()
+        .map(|contents| procfs::get_loadavg_from_reader(contents.as_bytes()))
+}
+
+// unix secs, fixed until reboot, 0 when unreadable
+static G_BOOT_TIME: Lazy<u64> = Lazy::new(|| {
+    RealProcFs
+        .read_stat()
+        .ok()
+        .and_then(|contents| procfs::get_btime_from_reader(contents.as_bytes()))
+        .unwrap_or(0)
+});
+
+pub fn get_boot_time() -> u64 {
+    *G_BOOT_TIME
+}
+
+pub fn get_loadavg<P: ProcFs>(fs: &P) -> (f64, f64, f64) {
+    try_get_loadavg(fs).unwrap_or_default()
+}
+
+// bytes, (mem_total, mem_used, swap_total, swap_free)
+pub fn try_get_memory<P: ProcFs>(fs: &P) -> io::Result<(u64, u64, u64, u64)> {
+    fs.read_meminfo().map(|contents| {
+        let (mem_total, mem_used, swap_total, swap_free) =
+            procfs::get_memory_from_reader(contents.as_bytes());
+        (
+            mem_total * KIB,
+            mem_used * KIB,
+            swap_total * KIB,
+            swap_free * KIB,
+        )
+    })
+}
+
+pub fn get_memory<P: ProcFs>(fs: &P) -> (u64, u64, u64, u64) {
+    try_get_memory(fs).unwrap_or_default()
+}
+
+// KiB, (dirty, writeback)
+pub fn get_memory_dirty<P: ProcFs>(fs: &P) -> (u64, u64) {
+    fs.read_meminfo()
+        .map(|contents| procfs::get_memory_dirty_from_reader(contents.as_bytes()))
+        .unwrap_or_default()
+}
+
+// (Committed_AS, CommitLimit) KiB
+pub fn get_memory_commit<P: ProcFs>(fs: &P) -> (u64, u64) {
+    fs.read_meminfo()
+        .map(|contents| procfs::get_memory_commit_from_reader(contents.as_bytes()))
+        .unwrap_or_default()
+}
+
+static THP_ENABLED_PATH: &str = "/sys/kernel/mm/transparent_hugepage/enabled";
+
+// (always|madvise|never, AnonHugePages KiB), mode empty without thp support
+pub fn get_thp<P: ProcFs>(fs: &P) -> (String, u64) {
+    let mode = fs::read_to_string(THP_ENABLED_PATH)
+        .ok()
+        .and_then(|contents| procfs::get_thp_mode(&contents))
+        .unwrap_or_default();
+    let anon_kb = fs
+        .read_meminfo()
+        .map(|contents| procfs::get_anon_huge_pages_from_reader(contents.as_bytes()))
+        .unwrap_or(0);
+    (mode, anon_kb)
+}
+
+// bytes, CommitLimit under overcommit_memory=2, otherwise physical ram
+pub fn get_effective_memory_limit<P: ProcFs>(fs: &P) -> u64 {
+    let (mem_total, _, swap_total, _) = get_memory(fs);
+    if read_proc_u64(fs, "sys/vm/overcommit_memory") != Some(2) {
+        return mem_total;
+    }
+    // overcommit_kbytes takes precedence over overcommit_ratio when set
+    match read_proc_u64(fs, "sys/vm/overcommit_kbytes") {
+        Some(kbytes) if kbytes > 0 => swap_total + kbytes * KIB,
+        _ => {
+            let ratio = read_proc_u64(fs, "sys/vm/overcommit_ratio").unwrap_or(50);
+            swap_total + mem_total * ratio / 100
+        }
+    }
+}
+
+// (rss_kb, vms_kb)
+pub fn get_self_memory_usage<P: ProcFs>(fs: &P) -> (u64, u64) {
+    fs.read_file("self/status")
+        .map(|contents| procfs::get_self_memory_from_reader(contents.as_bytes()))
+        .unwrap_or_default()
+}
+
+fn read_proc_u64<P: ProcFs>(fs: &P, name: &str) -> Option<u64> {
+    fs.read_file(name)
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+}
+
+// (count, max), zeros if nf_conntrack not loaded
+pub fn get_conntrack<P: ProcFs>(fs: &P) -> (u64, u64) {
+    let count = read_proc_u64(fs, "sys/net/netfilter/nf_conntrack_count").or_else(|| {
+        fs.read_file("net/nf_conntrack")
+            .ok()
+            .map(|s| s.lines().count() as u64)
+    });
+    match count {
+        Some(count) => (
+            count,
+            read_proc_u64(fs, "sys/net/netfilter/nf_conntrack_max").unwrap_or(0),
+        ),
+        None => (0, 0),
+    }
+}
+
+// below this tls handshakes and key generation may block on older kernels,
+// since 5.18 entropy_avail reads a constant 256
+pub const ENTROPY_LOW: u32 = 256;
+
+// (entropy_avail, poolsize) in bits, zeros if unreadable
+pub fn get_entropy_available<P: ProcFs>(fs: &P) -> (u32, u32) {
+    match read_proc_u64(fs, "sys/kernel/random/entropy_avail") {
+        Some(avail) => (
+            avail as u32,
+            read_proc_u64(fs, "sys/kernel/random/poolsize").unwrap_or(0) as u32,
+        ),
+        None => (0, 0),
+    }
+}
+
+static CONTAINER_CGROUP_VEC: &[&str] = &["docker", "kubepods", "containerd", "lxc"];
+fn detect_container<P: ProcFs>(fs: &P) -> bool {
+    fs::metadata("/.dockerenv").is_ok()
+        || fs
+            .read_file("1/cgroup")
+            .map(|s| CONTAINER_CGROUP_VEC.iter().any(|sk| s.contains(*sk)))
+            .unwrap_or(false)
+}
+
+lazy_static! {
+    static ref G_IN_CONTAINER: bool = detect_container(&RealProcFs);
+}
+
+pub fn is_in_container() -> bool {
+    *G_IN_CONTAINER
+}
+
+// (runtime, id), empty on bare metal
+pub fn get_container_info<P: ProcFs>(fs: &P) -> (String, String) {
+    fs.read_file("self/cgroup")
+        .ok()
+        .and_then(|contents| procfs::get_container_from_cgroup_reader(contents.as_bytes()))
+        .or_else(|| {
+            fs.read_file("self/mountinfo").ok().and_then(|contents| {
+                procfs::get_container_from_mountinfo_reader(contents.as_bytes())
+            })
+        })
+        .map(|(runtime, id)| (runtime.to_string(), id))
+        .unwrap_or_default()
+}
+
+lazy_static! {
+    static ref G_CONTAINER_INFO: (String, String) = get_container_info(&RealProcFs);
+}
+
+static CGROUP2_DIR: &str = "/sys/fs/cgroup";
+fn read_cgroup2(name: &str) -> Option<String> {
+    fs::read_to_string(Path::new(CGROUP2_DIR).join(name))
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+// cgroup v2, bytes (limit, current), None when unlimited or v1
+pub fn get_cgroup_memory() -> Option<(u64, u64)> {
+    let limit = read_cgroup2("memory.max")?.parse::<u64>().ok()?;
+    let current = read_cgroup2("memory.current")
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(0);
+    Some((limit, current))
+}
+
+// cgroup v2 `cpu.max` quota / period rounded up, None when unlimited or v1
+pub fn get_cgroup_cpus() -> Option<u32> {
+    let s = read_cgroup2("cpu.max")?;
+    let (quota, period) = s.split_once(' ')?;
+    let (quota, period) = (quota.parse::<u64>().ok()?, period.parse::<u64>().ok()?);
+    if period == 0 {
+        return None;
+    }
+    Some(((quota + period - 1) / period) as u32)
+}
+
+lazy_static! {
+    static ref G_CPU_TOPOLOGY: (String, u32, u32) = RealProcFs
+        .read_file("cpuinfo")
+        .map(|contents| procfs::get_cpuinfo_from_reader(contents.as_bytes()))
+        .unwrap_or_default();
+}
+
+// (model, physical cores, logical cores), read once
+pub fn get_cpu_topology() -> (String, u32, u32) {
+    G_CPU_TOPOLOGY.clone()
+}
+
+// eg: Europe/Berlin, /etc/timezone first, then the /etc/localtime symlink target
+fn get_timezone_name() -> String {
+    if let Ok(s) = fs::read_to_string("/etc/timezone") {
+        if !s.trim().is_empty() {
+            return s.trim().to_string();
+        }
+    }
+    fs::read_link("/etc/localtime")
+        .ok()
+        .and_then(|target| {
+            let target = target.to_string_lossy().to_string();
+            target
+                .split_once("zoneinfo/")
+                .map(|(_, name)| name.to_string())
+        })
+        .unwrap_or_default()
+}
+
+lazy_static! {
+    static ref G_TIMEZONE: String = get_timezone_name();
+}
+
+// (tz name, current utc offset secs)
+pub fn get_timezone() -> (String, i32) {
+    (
+        G_TIMEZONE.to_string(),
+        Local::now().offset().local_minus_utc(),
+    )
+}
+
+static LIB_MODULES_DIR: &str = "/lib/modules";
+static REBOOT_REQUIRED_FILE: &str = "/run/reboot-required";
+static REBOOT_REQUIRED_PKGS_FILE: &str = "/run/reboot-required.pkgs";
+// eg: 5.10.0-21-amd64 => [5, 10, 0, 21, 64]
+fn kernel_version_key(release: &str) -> Vec<u64> {
+    release
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|s| s.parse::<u64>().ok())
+        .collect()
+}
+
+static REBOOT_UNKNOWN_WARNED: AtomicBool = AtomicBool::new(false);
+
+// newer kernel installed than the running one, or debian's reboot-required flag
+pub fn get_reboot_required<P: ProcFs>(fs: &P) -> bool {
+    if fs::metadata(REBOOT_REQUIRED_FILE).is_ok() {
+        return true;
+    }
+    let running = match fs.read_file("sys/kernel/osrelease") {
+        Ok(s) => s.trim().to_string(),
+        Err(err) => {
+            if !REBOOT_UNKNOWN_WARNED.swap(true, Ordering::Relaxed) {
+                warn!("read osrelease => {:?}, reboot required unknown", err);
+            }
+            return false;
+        }
+    };
+    fs::read_dir(LIB_MODULES_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .map(|name| kernel_version_key(&name))
+                .max()
+                .map_or(false, |newest| newest > kernel_version_key(&running))
+        })
+        .unwrap_or(false)
+}
+
+// packages asking for the reboot, debian/ubuntu only
+pub fn get_reboot_required_pkgs() -> Vec<String> {
+    fs::read_to_string(REBOOT_REQUIRED_PKGS_FILE)
+        .map(|s| {
+            s.lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// failed unit names, `systemctl --failed`
+pub fn get_failed_units() -> Vec<String> {
+    Command::new("systemctl")
+        .args(["--failed", "--no-legend", "--plain"])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|l| l.split_whitespace().next())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub const TOP_TALKERS_MAX: usize = 10;
+
+// remote ips by established connection count, most first, ties by ip
+pub fn get_top_talkers<P: ProcFs>(fs: &P, n: usize) -> Vec<TopTalker> {
+    let mut counts: HashMap<IpAddr, u32> = HashMap::new();
+    for name in ["net/tcp", "net/tcp6"] {
+        let _ = fs.read_file(name).map(|contents| {
+            for addr in procfs::get_tcp_remote_addrs_from_reader(contents.as_bytes()) {
+                *counts.entry(addr).or_insert(0) += 1;
+            }
+        });
+    }
+    let mut talkers = counts.into_iter().collect::<Vec<_>>();
+    talkers.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    talkers
+        .into_iter()
+        .take(n.min(TOP_TALKERS_MAX))
+        .map(|(ip, connections)| TopTalker {
+            ip: ip.to_string(),
+            connections,
+        })
+        .collect()
+}
+
+// max accept backlog across listening sockets, warns per backlogged port
+pub fn get_tcp_listen_backlog<P: ProcFs>(fs: &P) -> u32 {
+    let mut backlog_max = 0;
+    for name in ["net/tcp", "net/tcp6"] {
+        let _ = fs.read_file(name).map(|contents| {
+            for (port, backlog) in procfs::get_tcp_listen_backlog_from_reader(contents.as_bytes()) {
+                if backlog > 0 {
+                    warn!("tcp listen port {} backlog => {}", port, backlog);
+                }
+                backlog_max = backlog_max.max(backlog);
             }
-            0
+        });
+    }
+    backlog_max
+}
+
+static RPI_THROTTLED_SYSFS: &str = "/sys/devices/platform/soc/soc:firmware/get_throttled";
+
+lazy_static! {
+    static ref G_IS_RPI: bool = fs::read_to_string("/proc/device-tree/model")
+        .map(|model| model.contains("Raspberry Pi"))
+        .unwrap_or(false);
+}
+
+pub fn is_rpi() -> bool {
+    *G_IS_RPI
+}
+
+static RAPL_DIR: &str = "/sys/class/powercap/intel-rapl:0";
+
+lazy_static! {
+    // (energy_uj, read at)
+    static ref G_RAPL_PRE: Mutex<Option<(u64, Instant)>> = Mutex::new(None);
+}
+
+fn read_rapl_u64(name: &str) -> Option<u64> {
+    fs::read_to_string(Path::new(RAPL_DIR).join(name))
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+}
+
+// package 0 average watts since the previous call, None on the first call,
+// without rapl, or when energy_uj is root only (kernels after CVE-2020-8694)
+pub fn get_rapl_power_watts() -> Option<f64> {
+    let energy_uj = read_rapl_u64("energy_uj")?;
+    let now = Instant::now();
+    let pre = G_RAPL_PRE.lock().ok()?.replace((energy_uj, now));
+    let (pre_uj, pre_at) = pre?;
+    let secs = now.duration_since(pre_at).as_secs_f64();
+    if secs <= 0.0 {
+        return None;
+    }
+    // the counter wraps at max_energy_range_uj
+    let delta_uj = if energy_uj >= pre_uj {
+        energy_uj - pre_uj
+    } else {
+        read_rapl_u64("max_energy_range_uj")?.saturating_sub(pre_uj) + energy_uj
+    };
+    Some(delta_uj as f64 / 1e6 / secs)
+}
+
+// (throttled now, reasons), the firmware sysfs file first, vcgencmd on older kernels
+pub fn get_rpi_throttled(allow_vcgencmd: bool) -> (bool, Vec<String>) {
+    let mask = fs::read_to_string(RPI_THROTTLED_SYSFS)
+        .ok()
+        .and_then(|s| procfs::get_throttled_from_str(&s))
+        .or_else(|| {
+            if !allow_vcgencmd {
+                return None;
+            }
+            Command::new("vcgencmd")
+                .arg("get_throttled")
+                .output()
+                .ok()
+                .and_then(|output| {
+                    procfs::get_throttled_from_str(&String::from_utf8_lossy(&output.stdout))
+                })
+        })
+        .unwrap_or(0);
+    let reasons = procfs::get_throttle_reasons(mask);
+    (!reasons.is_empty(), reasons)
+}
+
+lazy_static! {
+    static ref G_LOCKUP_TOTAL: Mutex<Option<u64>> = Mutex::new(None);
+}
+
+// lockups since the previous call, the first call only sets the baseline,
+// dmesg rather than /proc/kmsg which is a destructive read and would steal lines from syslog,
+// 0 when the kernel log is not readable (kernel.dmesg_restrict)
+pub fn get_lockup_events() -> u64 {
+    let total = match Command::new("dmesg").output() {
+        Ok(output) if output.status.success() => {
+            procfs::get_lockup_count_from_reader(&output.stdout[..])
+        }
+        _ => return 0,
+    };
+    let mut prev = match G_LOCKUP_TOTAL.lock() {
+        Ok(prev) => prev,
+        Err(_) => return 0,
+    };
+    // the ring buffer wraps, a shrinking total is not an event
+    let delta = prev.map_or(0, |prev| total.saturating_sub(prev));
+    *prev = Some(total);
+    delta
+}
+
+pub const KERNEL_ERRORS_MAX: usize = 20;
+
+// the last n error lines of the kernel log, dmesg for the same reason as get_lockup_events,
+// empty when the kernel log is not readable
+pub fn get_recent_kernel_errors(n: usize) -> Vec<String> {
+    match Command::new("dmesg").output() {
+        Ok(output) if output.status.success() => {
+            procfs::get_kernel_errors_from_reader(&output.stdout[..], n.min(KERNEL_ERRORS_MAX))
+        }
+        _ => Vec::new(),
+    }
+}
+
+// numeric /proc entries with PF_KTHREAD in the stat flags, 0 if the scan fails
+pub fn get_kernel_thread_count<P: ProcFs>(fs: &P) -> u32 {
+    let pids = match fs.list_pids() {
+        Ok(pids) => pids,
+        Err(_) => return 0,
+    };
+    pids.into_iter()
+        .filter_map(|pid| fs.read_file(&format!("{}/stat", pid)).ok())
+        .filter_map(|contents| procfs::get_pid_stat_flags(&contents))
+        .filter(|flags| flags & procfs::PF_KTHREAD != 0)
+        .count() as u32
+}
+
+// bytes, (size, c_max)
+pub fn get_zfs_arc<P: ProcFs>(fs: &P) -> (u64, u64) {
+    fs.read_file("spl/kstat/zfs/arcstats")
+        .map(|contents| procfs::get_zfs_arcstats_from_reader(contents.as_bytes()))
+        .unwrap_or_default()
+}
+
+// explicit path first, then the same file name on $PATH
+fn resolve_vnstat_path(path: &Path) -> PathBuf {
+    if path.exists() {
+        return path.to_path_buf();
+    }
+    let name = path.file_name().unwrap_or_else(|| OsStr::new("vnstat"));
+    env::var_os("PATH")
+        .and_then(|paths| {
+            env::split_paths(&paths)
+                .map(|dir| dir.join(name))
+                .find(|p| p.is_file())
+        })
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+// zeros when vnstat is missing or prints something unexpected
+pub fn get_vnstat_traffic(path: &Path) -> (u64, u64, u64, u64) {
+    let local_now = Local::now();
+    let (mut network_in, mut network_out, mut m_network_in, mut m_network_out) = (0, 0, 0, 0);
+    let j: serde_json::Value = match Command::new(resolve_vnstat_path(path))
+        .args(["--json", "m"])
+        .output()
+        .map_err(|err| err.to_string())
+        .and_then(|output| serde_json::from_slice(&output.stdout).map_err(|err| err.to_string()))
+    {
+        Ok(j) => j,
+        Err(err) => {
+            error!("vnstat => {}", err);
+            return (0, 0, 0, 0);
+        }
+    };
+    for iface in j["interfaces"].as_array().into_iter().flatten() {
+        let name = iface["name"].as_str().unwrap_or_default();
+        if IFACE_IGNORE_VEC.iter().any(|sk| name.contains(*sk)) {
+            continue;
+        }
+        let total = &iface["traffic"]["total"];
+        network_in += total["rx"].as_u64().unwrap_or(0);
+        network_out += total["tx"].as_u64().unwrap_or(0);
+
+        for data in iface["traffic"]["month"].as_array().into_iter().flatten() {
+            let year = data["date"]["year"].as_i64().unwrap_or(0) as i32;
+            let month = data["date"]["month"].as_i64().unwrap_or(0) as u32;
+            if local_now.year() != year || local_now.month() != month {
+                continue;
+            }
+
+            m_network_in += data["rx"].as_u64().unwrap_or(0);
+            m_network_out += data["tx"].as_u64().unwrap_or(0);
+        }
+    }
+
+    (network_in, network_out, m_network_in, m_network_out)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn try_get_sys_traffic<P: ProcFs>(fs: &P) -> io::Result<(u64, u64)> {
+    fs.read_net_dev()
+        .map(|contents| procfs::get_sys_traffic_from_reader(contents.as_bytes()))
+}
+
+#[cfg(target_os = "macos")]
+pub fn try_get_sys_traffic<P: ProcFs>(_fs: &P) -> io::Result<(u64, u64)> {
+    Command::new("netstat")
+        .args(["-ib", "-n"])
+        .output()
+        .map(|output| procfs::get_netstat_traffic_from_reader(&output.stdout[..]))
+}
+
+pub fn get_sys_traffic<P: ProcFs>(fs: &P) -> (u64, u64) {
+    try_get_sys_traffic(fs).unwrap_or_default()
+}
+
+pub fn get_sys_packets<P: ProcFs>(fs: &P) -> (u64, u64) {
+    fs.read_net_dev()
+        .map(|contents| procfs::get_sys_packets_from_reader(contents.as_bytes()))
+        .unwrap_or_default()
+}
+
+// nftables rule counter, iptables as fallback, spec is table:chain:comment
+pub fn get_nft_counter(spec: &str) -> Option<u64> {
+    let vec = spec.splitn(3, ':').collect::<Vec<_>>();
+    if vec.len() != 3 {
+        warn!("invalid traffic nft spec => {}", spec);
+        return None;
+    }
+    let (table, chain, comment) = (vec[0], vec[1], vec[2]);
+
+    let nft = Command::new("nft")
+        .args(["-j", "list", "ruleset"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            procfs::get_nft_counter_bytes(
+                &String::from_utf8_lossy(&output.stdout),
+                table,
+                chain,
+                comment,
+            )
+        });
+    if nft.is_some() {
+        return nft;
+    }
+    Command::new("iptables")
+        .args(["-nvx", "-L", chain, "-t", table])
+        .output()
+        .ok()
+        .and_then(|output| {
+            procfs::get_iptables_counter_bytes_from_reader(&output.stdout[..], comment)
+        })
+}
+
+// (in, out) bytes, the first spec counts as in, the optional second as out
+pub fn get_nft_traffic(specs: &[String]) -> (u64, u64) {
+    let get = |idx: usize| {
+        specs
+            .get(idx)
+            .and_then(|spec| get_nft_counter(spec))
+            .unwrap_or(0)
+    };
+    (get(0), get(1))
+}
+
+const NETNS_RUN_DIR: &str = "/var/run/netns";
+
+// --net-ns-pid or --netns, an `ip netns add` name is a bind mount under NETNS_RUN_DIR
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetNs {
+    Pid(u32),
+    Named(String),
+}
+
+impl NetNs {
+    pub fn path(&self) -> PathBuf {
+        match self {
+            NetNs::Pid(pid) => PathBuf::from(format!("/proc/{}/ns/net", pid)),
+            NetNs::Named(name) => Path::new(NETNS_RUN_DIR).join(name),
+        }
+    }
+}
+
+// a bare name, the path is built under NETNS_RUN_DIR
+pub fn parse_netns_name(s: &str) -> Result<String, String> {
+    if s.is_empty() || s == "." || s == ".." || s.contains('/') {
+        return Err(format!("invalid netns name `{}`", s));
+    }
+    Ok(s.to_string())
+}
+
+#[cfg(target_os = "linux")]
+pub fn open_net_ns(ns: &NetNs) -> io::Result<fs::File> {
+    fs::File::open(ns.path())
+}
+
+// setns is per thread, runs f inside the net namespace and switches back,
+// /proc/thread-self/net then reflects that namespace while /proc/net keeps the leader's
+#[cfg(target_os = "linux")]
+pub fn in_net_ns<T>(ns: &NetNs, f: impl FnOnce() -> T) -> io::Result<T> {
+    use nix::sched::{setns, CloneFlags};
+    let orig = fs::File::open("/proc/thread-self/ns/net")?;
+    let target = open_net_ns(ns)?;
+    setns(target.as_raw_fd(), CloneFlags::CLONE_NEWNET).map_err(io::Error::from)?;
+    let res = f();
+    if let Err(err) = setns(orig.as_raw_fd(), CloneFlags::CLONE_NEWNET) {
+        error!("restore net namespace => {:?}", err);
+    }
+    Ok(res)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn in_net_ns<T>(_ns: &NetNs, f: impl FnOnce() -> T) -> io::Result<T> {
+    Ok(f())
+}
+
+// net/* from the given net namespace, everything else as RealProcFs
+pub struct NetNsProcFs(pub Option<NetNs>);
+
+impl ProcFs for NetNsProcFs {
+    fn read_file(&self, name: &str) -> io::Result<String> {
+        match &self.0 {
+            Some(ns) if name.starts_with("net/") => in_net_ns(ns, || {
+                fs::read_to_string(format!("/proc/thread-self/{}", name))
+            })?,
+            _ => RealProcFs.read_file(name),
+        }
+    }
+
+    fn list_pids(&self) -> io::Result<Vec<u32>> {
+        RealProcFs.list_pids()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrafficSource {
+    Nft,
+    Vnstat,
+    Proc,
+}
+
+static G_TRAFFIC_SOURCE: OnceCell<TrafficSource> = OnceCell::new();
+
+fn parse_traffic_source(name: &str) -> Option<TrafficSource> {
+    match name {
+        "nft" | "iptables" => Some(TrafficSource::Nft),
+        "vnstat" => Some(TrafficSource::Vnstat),
+        "proc" => Some(TrafficSource::Proc),
+        _ => None,
+    }
+}
+
+fn probe_traffic_source(args: &Args, source: TrafficSource) -> bool {
+    match source {
+        TrafficSource::Nft => args
+            .traffic_nft
+            .first()
+            .map_or(false, |spec| get_nft_counter(spec).is_some()),
+        TrafficSource::Vnstat => Command::new(resolve_vnstat_path(&args.vnstat_path))
+            .args(["--json", "m"])
+            .output()
+            .map_or(false, |output| {
+                output.status.success()
+                    && serde_json::from_slice::<serde_json::Value>(&output.stdout)
+                        .map_or(false, |j| j["interfaces"].is_array())
+            }),
+        TrafficSource::Proc => get_sys_traffic(&NetNsProcFs(args.net_ns())) != (0, 0),
+    }
+}
+
+// without --traffic-prefer the flags decide as before, otherwise the first working source wins
+fn select_traffic_source(args: &Args) -> TrafficSource {
+    if args.traffic_prefer.is_empty() {
+        return if !args.traffic_nft.is_empty() {
+            TrafficSource::Nft
+        } else if args.vnstat {
+            TrafficSource::Vnstat
+        } else {
+            TrafficSource::Proc
+        };
+    }
+
+    let mut prefer = Vec::new();
+    for name in &args.traffic_prefer {
+        if name.eq("auto") {
+            prefer.extend([
+                TrafficSource::Nft,
+                TrafficSource::Vnstat,
+                TrafficSource::Proc,
+            ]);
+        } else if let Some(source) = parse_traffic_source(name) {
+            prefer.push(source);
+        } else {
+            warn!("unknown traffic source => {}", name);
+        }
+    }
+    let source = prefer
+        .into_iter()
+        .find(|source| {
+            let ok = probe_traffic_source(args, *source);
+            if !ok {
+                info!("traffic source {:?} unavailable, skipped", source);
+            }
+            ok
+        })
+        .unwrap_or(TrafficSource::Proc);
+    info!("traffic source => {:?}", source);
+    source
+}
+
+pub fn traffic_source(args: &Args) -> TrafficSource {
+    *G_TRAFFIC_SOURCE.get_or_init(|| select_traffic_source(args))
+}
+
+// (rx_errors, tx_errors, rx_dropped, tx_dropped)
+pub fn get_net_errors<P: ProcFs>(fs: &P) -> (u64, u64, u64, u64) {
+    fs.read_net_dev()
+        .map(|contents| procfs::get_net_errors_from_reader(contents.as_bytes()))
+        .unwrap_or_default()
+}
+
+pub fn get_softnet_stats<P: ProcFs>(fs: &P) -> procfs::SoftnetStats {
+    fs.read_file("net/softnet_stat")
+        .map(|contents| procfs::get_softnet_stats_from_reader(contents.as_bytes()))
+        .unwrap_or_default()
+}
+
+static SOFTNET_DROPPED_WARNED: AtomicBool = AtomicBool::new(false);
+static SOFTNET_SQUEEZE_WARNED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Default)]
+pub struct NetSpeed {
+    pub diff: f64,
+    pub clock: f64,
+    pub netrx: u64,
+    pub nettx: u64,
+    pub avgrx: u64,
+    pub avgtx: u64,
+    pub ts_ms: u64,
+    // iface => (rx bytes, tx bytes), (rx bytes/s, tx bytes/s)
+    pub iface_avg: HashMap<String, (u64, u64)>,
+    pub iface_speed: HashMap<String, (u64, u64)>,
+}
+
+lazy_static! {
+    pub static ref G_NET_SPEED: Arc<Mutex<NetSpeed>> = Arc::new(Default::default());
+}
+
+// set once the collector has a real delta, before that rates read 0, see --warmup
+pub static G_NET_WARM: AtomicBool = AtomicBool::new(false);
+pub static G_CPU_WARM: AtomicBool = AtomicBool::new(false);
+
+// true when every enabled collector warmed up within timeout
+#[allow(unused)]
+pub fn wait_warmup(cpu: bool, net: bool, timeout: Duration) -> bool {
+    let start = Instant::now();
+    loop {
+        let warm = (!cpu || G_CPU_WARM.load(Ordering::Relaxed))
+            && (!net || G_NET_WARM.load(Ordering::Relaxed));
+        if warm {
+            return true;
+        }
+        if start.elapsed() >= timeout {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[allow(unused)]
+pub fn start_net_speed_collect_t(net_ns: Option<NetNs>) {
+    // a proc net file stays bound to the namespace it was opened in
+    #[cfg(target_os = "linux")]
+    let mut net_dev = match &net_ns {
+        Some(ns) => {
+            in_net_ns(ns, || procfs::ProcFile::open("/proc/thread-self/net/dev")).and_then(|f| f)
+        }
+        None => procfs::ProcFile::open(procfs::proc_path("net/dev")),
+    };
+    #[cfg(target_os = "linux")]
+    if let Err(err) = net_dev.as_ref() {
+        error!("open net/dev => {:?}", err);
+    }
+    thread::spawn(move || loop {
+        #[cfg(target_os = "linux")]
+        let ((avgrx, avgtx), iface_avg) = net_dev
+            .as_mut()
+            .ok()
+            .and_then(|f| f.read().ok())
+            .map(|contents| {
+                (
+                    procfs::get_sys_traffic_from_reader(contents.as_bytes()),
+                    procfs::get_iface_traffic_from_reader(contents.as_bytes()),
+                )
+            })
+            .unwrap_or_default();
+        #[cfg(not(target_os = "linux"))]
+        let ((avgrx, avgtx), iface_avg) = (
+            get_sys_traffic(&NetNsProcFs(net_ns.clone())),
+            HashMap::new(),
+        );
+
+        // the rate diff off the cheap monotonic clock, the reported ts stays wall clock
+        let now = clock::fast_monotonic_nanos() as f64 / 1e9;
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+
+        if let Ok(mut t) = G_NET_SPEED.lock() {
+            if t.clock > 0.0 {
+                G_NET_WARM.store(true, Ordering::Relaxed);
+            }
+            t.ts_ms = now_ms;
+            t.diff = now - t.clock;
+            t.clock = now;
+            t.netrx = procfs::calc_speed(avgrx, t.avgrx, t.diff);
+            t.nettx = procfs::calc_speed(avgtx, t.avgtx, t.diff);
+            t.avgrx = avgrx;
+            t.avgtx = avgtx;
+
+            let diff = t.diff;
+            let first_scan = t.iface_avg.is_empty();
+            let mut iface_speed = HashMap::new();
+            for (name, &(rx, tx)) in &iface_avg {
+                match t.iface_avg.get(name) {
+                    Some(&(pre_rx, pre_tx)) => {
+                        iface_speed.insert(
+                            name.to_string(),
+                            (
+                                procfs::calc_speed(rx, pre_rx, diff),
+                                procfs::calc_speed(tx, pre_tx, diff),
+                            ),
+                        );
+                    }
+                    // seeded with its current counters, no speed until the next scan
+                    None if !first_scan => info!("New interface detected: {}", name),
+                    None => {}
+                }
+            }
+            // sum per iface so ifaces coming and going don't spike the total
+            if !iface_avg.is_empty() && !first_scan {
+                t.netrx = iface_speed.values().map(|v| v.0).sum();
+                t.nettx = iface_speed.values().map(|v| v.1).sum();
+            }
+            t.iface_speed = iface_speed;
+            t.iface_avg = iface_avg;
+
+            debug!("net speed => {:?}", t);
+        }
+
+        thread::sleep(Duration::from_millis(SAMPLE_PERIOD));
+    });
+}
+
+static SYS_NET_DIR: &str = "/sys/class/net";
+// iface => rated speed Mbps, virtual ifaces without a known speed are left out
+pub fn get_iface_link_info() -> HashMap<String, u64> {
+    fs::read_dir(SYS_NET_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    // -1 or EINVAL when unknown
+                    let mbps = fs::read_to_string(entry.path().join("speed"))
+                        .ok()?
+                        .trim()
+                        .parse::<i64>()
+                        .ok()?;
+                    (mbps > 0).then(|| (name, mbps as u64))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// monitored ifaces, speed 0 when unknown, ifaces without a readable mtu or duplex,
+// eg: virtual ones where duplex is EINVAL, are left out
+pub fn get_iface_links() -> Vec<IfaceLink> {
+    let mut links = fs::read_dir(SYS_NET_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if IFACE_IGNORE_VEC.iter().any(|sk| name.contains(*sk)) {
+                        return None;
+                    }
+                    let read = |file: &str| fs::read_to_string(entry.path().join(file)).ok();
+                    let mtu = read("mtu")?.trim().parse::<u32>().ok()?;
+                    let duplex = read("duplex")?.trim().to_string();
+                    let speed_mbps = read("speed")
+                        .and_then(|speed| speed.trim().parse::<i64>().ok())
+                        .filter(|&mbps| mbps > 0)
+                        .unwrap_or(0) as u64;
+                    Some(IfaceLink {
+                        name,
+                        speed_mbps,
+                        mtu,
+                        duplex,
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    links.sort_by(|a, b| a.name.cmp(&b.name));
+    links
+}
+
+// max (rx %, tx %) across ifaces of known speed, -1.0 if none
+pub fn get_net_utilization(iface_speed: &HashMap<String, (u64, u64)>) -> (f64, f64) {
+    let (mut rx_pct, mut tx_pct) = (-1.0_f64, -1.0_f64);
+    for (name, mbps) in get_iface_link_info() {
+        if let Some(&(rx, tx)) = iface_speed.get(&name) {
+            let bytes_per_sec = mbps as f64 * 1_000_000.0 / 8.0;
+            rx_pct = rx_pct.max(100.0 * rx as f64 / bytes_per_sec);
+            tx_pct = tx_pct.max(100.0 * tx as f64 / bytes_per_sec);
+        }
+    }
+    (rx_pct, tx_pct)
+}
+
+static DISK_IGNORE_VEC: &[&str] = &["loop", "ram", "zram", "dm-", "sr", "fd"];
+// whole physical disk, skip partitions and virtual devices
+fn is_physical_disk(name: &str) -> bool {
+    !DISK_IGNORE_VEC.iter().any(|sk| name.starts_with(*sk))
+        && std::path::Path::new("/sys/block").join(name).exists()
+}
+
+// io scheduler and requests in flight, sorted by name
+pub fn get_disk_queues() -> Vec<DiskQueue> {
+    let mut disks = fs::read_dir("/sys/block")
+        .map(|dir| {
+            dir.filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| is_physical_disk(name))
+                .map(|name| {
+                    let dev = std::path::Path::new("/sys/block").join(&name);
+                    DiskQueue {
+                        scheduler: fs::read_to_string(dev.join("queue/scheduler"))
+                            .ok()
+                            .and_then(|contents| procfs::get_block_scheduler(&contents))
+                            .unwrap_or_default(),
+                        inflight: fs::read_to_string(dev.join("inflight"))
+                            .ok()
+                            .and_then(|contents| procfs::get_block_inflight(&contents))
+                            .unwrap_or_default(),
+                        name,
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    disks.sort_by(|a, b| a.name.cmp(&b.name));
+    disks
+}
+
+lazy_static! {
+    pub static ref G_DISK_AWAIT: Arc<Mutex<HashMap<String, f64>>> = Arc::new(Default::default());
+}
+
+pub fn start_disk_io_collect_t() {
+    let mut pre_stats: HashMap<String, procfs::DiskStat> = HashMap::new();
+    thread::spawn(move || loop {
+        let _ = RealProcFs.read_file("diskstats").map(|contents| {
+            let cur_stats = procfs::get_diskstats_from_reader(contents.as_bytes())
+                .into_iter()
+                .filter(|(name, _)| is_physical_disk(name))
+                .collect::<HashMap<_, _>>();
+
+            let mut disk_await = HashMap::new();
+            for (name, cur) in &cur_stats {
+                if let Some(pre) = pre_stats.get(name) {
+                    let ios = procfs::wrapping_diff(
+                        cur.reads.wrapping_add(cur.writes),
+                        pre.reads.wrapping_add(pre.writes),
+                    );
+                    let ticks = procfs::wrapping_diff(
+                        cur.read_ms.wrapping_add(cur.write_ms),
+                        pre.read_ms.wrapping_add(pre.write_ms),
+                    );
+                    let await_ms = if ios == 0 {
+                        0.0
+                    } else {
+                        ticks as f64 / ios as f64
+                    };
+                    disk_await.insert(name.to_string(), (await_ms * 100.0).round() / 100.0);
+                }
+            }
+            pre_stats = cur_stats;
+
+            if let Ok(mut o) = G_DISK_AWAIT.lock() {
+                *o = disk_await;
+            }
+        });
+
+        thread::sleep(Duration::from_millis(SAMPLE_PERIOD));
+    });
+}
+
+lazy_static! {
+    pub static ref G_IRQ_RATE: Arc<AtomicU64> = Arc::new(AtomicU64::new(0));
+    pub static ref G_SOFTIRQ_RATE: Arc<AtomicU64> = Arc::new(AtomicU64::new(0));
+    // (NET_RX, NET_TX) percent of softirqs raised
+    pub static ref G_SOFTIRQ_NET: Arc<Mutex<(f64, f64)>> = Arc::new(Default::default());
+}
+
+pub fn start_irq_rate_collect_t() {
+    let mut pre: Option<(u64, u64, u64)> = None;
+    let mut pre_net: Option<(u64, u64, u64)> = None;
+    thread::spawn(move || loop {
+        let _ = RealProcFs.read_stat().map(|contents| {
+            let net = procfs::get_softirq_net_from_reader(contents.as_bytes());
+            if let (Some((total, tx, rx)), Some((pre_total, pre_tx, pre_rx))) = (net, pre_net) {
+                let diff_total = total.saturating_sub(pre_total).max(1) as f64;
+                if let Ok(mut o) = G_SOFTIRQ_NET.lock() {
+                    *o = (
+                        100.0 * rx.saturating_sub(pre_rx) as f64 / diff_total,
+                        100.0 * tx.saturating_sub(pre_tx) as f64 / diff_total,
+                    );
+                }
+            }
+            pre_net = net;
+
+            let (intr, softirq) = procfs::get_stat_irq_from_reader(contents.as_bytes());
+            let now = clock::fast_monotonic_nanos();
+            if let Some((pre_intr, pre_softirq, pre_ts)) = pre {
+                let diff = now.saturating_sub(pre_ts) as f64 / 1e9;
+                G_IRQ_RATE.store(procfs::calc_speed(intr, pre_intr, diff), Ordering::Relaxed);
+                G_SOFTIRQ_RATE.store(
+                    procfs::calc_speed(softirq, pre_softirq, diff),
+                    Ordering::Relaxed,
+                );
+            }
+            pre = Some((intr, softirq, now));
+        });
+
+        thread::sleep(Duration::from_millis(SAMPLE_PERIOD));
+    });
+}
+
+const LOAD_CUSTOM_SAMPLE_SECS: u64 = 2;
+
+lazy_static! {
+    pub static ref G_LOAD_CUSTOM: Arc<Mutex<f64>> = Arc::new(Default::default());
+}
+// plain mean of running + blocked tasks over --load-window-secs,
+// where loadavg decays exponentially over 1/5/15 minutes
+pub fn start_load_custom_collect_t(window_secs: u64) {
+    let samples = (window_secs / LOAD_CUSTOM_SAMPLE_SECS).max(1) as usize;
+    let mut window: VecDeque<u64> = VecDeque::with_capacity(samples + 1);
+    thread::spawn(move || loop {
+        if let Some((running, blocked)) = RealProcFs
+            .read_file("stat")
+            .ok()
+            .and_then(|contents| procfs::get_procs_running_from_reader(contents.as_bytes()))
+        {
+            // procs_running counts this thread reading /proc/stat
+            window.push_back(running.saturating_sub(1) + blocked);
+            if window.len() > samples {
+                window.pop_front();
+            }
+            let avg = window.iter().sum::<u64>() as f64 / window.len() as f64;
+            if let Ok(mut o) = G_LOAD_CUSTOM.lock() {
+                *o = (avg * 100.0).round() / 100.0;
+            }
+        }
+
+        thread::sleep(Duration::from_secs(LOAD_CUSTOM_SAMPLE_SECS));
+    });
+}
+
+const STEAL_WINDOW_SAMPLES: usize = 10;
+
+lazy_static! {
+    pub static ref G_CPU_PERCENT: Arc<Mutex<f64>> = Arc::new(Default::default());
+    // (steal percent, contended)
+    pub static ref G_CPU_STEAL: Arc<Mutex<(f64, bool)>> = Arc::new(Default::default());
+    pub static ref G_CPU_SOFTIRQ: Arc<Mutex<f64>> = Arc::new(Default::default());
+    pub static ref G_CPU_IOWAIT: Arc<Mutex<f64>> = Arc::new(Default::default());
+    pub static ref G_CPU_NICE: Arc<Mutex<f64>> = Arc::new(Default::default());
+}
+#[allow(unused)]
+pub fn start_cpu_percent_collect_t(avg_samples: usize, steal_threshold: f64) {
+    let mut pre_cpu: Vec<u64> = vec![0, 0, 0, 0];
+    // (idle, total) deltas of the last N samples
+    let avg_samples = avg_samples.max(1);
+    let mut window: VecDeque<(u64, u64)> = VecDeque::with_capacity(avg_samples);
+    // (steal, total incl. iowait/irq/steal) deltas
+    let mut pre_steal = (0_u64, 0_u64);
+    let mut pre_softirq = 0_u64;
+    let mut pre_iowait = 0_u64;
+    let mut steal_window: VecDeque<(u64, u64)> = VecDeque::with_capacity(STEAL_WINDOW_SAMPLES);
+    let mut stat_file = procfs::ProcFile::open(procfs::proc_path("stat"));
+    thread::spawn(move || loop {
+        if let Some(buf) = stat_file
+            .as_mut()
+            .ok()
+            .and_then(|f| f.read().ok())
+            .and_then(|s| s.lines().next())
+        {
+            // user nice system idle iowait irq softirq steal ...
+            let fields = buf
+                .split_whitespace()
+                .skip(1)
+                .map(|e| e.parse::<u64>().unwrap_or(0))
+                .collect::<Vec<_>>();
+            // sandboxed kernels, eg: gVisor, may serve a short or garbled line
+            if fields.len() < 4 {
+                thread::sleep(Duration::from_millis(SAMPLE_PERIOD));
+                continue;
+            }
+            let cur_cpu = fields[..4].to_vec();
+
+            let cur_steal = (
+                fields.get(7).copied().unwrap_or(0),
+                fields.iter().take(8).sum::<u64>(),
+            );
+            let cur_softirq = fields.get(6).copied().unwrap_or(0);
+            let cur_iowait = fields.get(4).copied().unwrap_or(0);
+            // the first delta spans the whole uptime, skip it
+            if pre_steal.1 > 0 {
+                if let Ok(mut o) = G_CPU_SOFTIRQ.lock() {
+                    *o = 100.0 * cur_softirq.saturating_sub(pre_softirq) as f64
+                        / cur_steal.1.saturating_sub(pre_steal.1).max(1) as f64;
+                }
+                // idle waiting on io, outside the cpu percent which only sums 4 fields
+                if let Ok(mut o) = G_CPU_IOWAIT.lock() {
+                    *o = 100.0 * cur_iowait.saturating_sub(pre_iowait) as f64
+                        / cur_steal.1.saturating_sub(pre_steal.1).max(1) as f64;
+                }
+                steal_window.push_back((
+                    cur_steal.0.saturating_sub(pre_steal.0),
+                    cur_steal.1.saturating_sub(pre_steal.1),
+                ));
+                if steal_window.len() > STEAL_WINDOW_SAMPLES {
+                    steal_window.pop_front();
+                }
+            }
+            pre_steal = cur_steal;
+            pre_softirq = cur_softirq;
+            pre_iowait = cur_iowait;
+            let (steal, total) = steal_window
+                .iter()
+                .fold((0, 0), |(steal, total), o| (steal + o.0, total + o.1));
+            let steal_pct = 100.0 * steal as f64 / total.max(1) as f64;
+            let contended =
+                steal_window.len() == STEAL_WINDOW_SAMPLES && steal_pct > steal_threshold;
+            if let Ok(mut o) = G_CPU_STEAL.lock() {
+                if contended && !o.1 {
+                    warn!(
+                        "cpu steal {:.1}% over {}s, vm contended",
+                        steal_pct, STEAL_WINDOW_SAMPLES
+                    );
+                }
+                *o = (steal_pct, contended);
+            }
+
+            let pre: u64 = pre_cpu.iter().sum();
+            let cur: u64 = cur_cpu.iter().sum();
+            // the first delta spans the whole uptime, it would outweigh the window
+            if pre > 0 {
+                window.push_back((
+                    cur_cpu[3].saturating_sub(pre_cpu[3]),
+                    cur.saturating_sub(pre),
+                ));
+                if window.len() > avg_samples {
+                    window.pop_front();
+                }
+            }
+
+            let (idle, st) = window
+                .iter()
+                .fold((0, 0), |(idle, st), o| (idle + o.0, st + o.1));
+            let res = 100.0 - (100.0 * idle as f64 / st.max(1) as f64);
+
+            debug!("pre_cpu => {:?}, cur_cpu => {:?}", pre_cpu, cur_cpu);
+
+            // niced share of the same 4 field total, so a part of cpu
+            if let Ok(mut o) = G_CPU_NICE.lock() {
+                *o = 100.0 * cur_cpu[1].saturating_sub(pre_cpu[1]) as f64
+                    / cur.saturating_sub(pre).max(1) as f64;
+            }
+
+            pre_cpu = cur_cpu;
+
+            if !window.is_empty() {
+                if let Ok(mut cpu_percent) = G_CPU_PERCENT.lock() {
+                    *cpu_percent = res.round();
+                    debug!("cpu percent => {}", cpu_percent);
+                }
+                G_CPU_WARM.store(true, Ordering::Relaxed);
+            }
+        }
+
+        thread::sleep(Duration::from_millis(SAMPLE_PERIOD));
+    });
+}
+
+pub const TOP_PROCS_MAX: usize = 10;
+
+lazy_static! {
+    pub static ref G_TOP_PROCS: Arc<Mutex<Vec<ProcInfo>>> = Arc::new(Default::default());
+}
+
+// pid => (comm, utime + stime)
+fn scan_proc_times() -> HashMap<u32, (String, u64)> {
+    RealProcFs
+        .list_pids()
+        .map(|pids| {
+            pids.into_iter()
+                .filter_map(|pid| {
+                    let contents = RealProcFs.read_file(&format!("{}/stat", pid)).ok()?;
+                    Some((pid, procfs::get_pid_stat_times(&contents)?))
+                })
+                .collect()
         })
-        .unwrap()
+        .unwrap_or_default()
 }
 
-pub fn get_loadavg() -> (f64, f64, f64) {
-    fs::read_to_string("/proc/loadavg")
-        .map(|contents| {
-            let vec = contents.split_whitespace().collect::<Vec<_>>();
-            // dbg!(&vec);
-            if vec.len() >= 3 {
-                let a = vec[0..3]
+// same delta method as the cpu collector, per pid ticks over all cpu ticks, scaled to one core
+pub fn start_top_procs_collect_t(n: usize, anonymize: bool) {
+    let n = n.min(TOP_PROCS_MAX);
+    thread::spawn(move || {
+        let mut pre_total = 0;
+        let mut pre_times: HashMap<u32, (String, u64)> = HashMap::new();
+        loop {
+            let (total, cpus) = RealProcFs
+                .read_stat()
+                .map(|contents| procfs::get_stat_cpu_total_from_reader(contents.as_bytes()))
+                .unwrap_or_default();
+            let times = scan_proc_times();
+
+            let diff_total = total.saturating_sub(pre_total);
+            if pre_total > 0 && diff_total > 0 {
+                let mut busy = times
                     .iter()
-                    .map(|v| v.parse::<f64>().unwrap())
-                    .collect::<Vec<f64>>();
+                    .filter_map(|(pid, (name, ticks))| {
+                        let (_, pre_ticks) = pre_times.get(pid)?;
+                        Some((*pid, name, ticks.saturating_sub(*pre_ticks)))
+                    })
+                    .collect::<Vec<_>>();
+                busy.sort_by_key(|o| std::cmp::Reverse(o.2));
 
-                return (a[0], a[1], a[2]);
+                let top = busy
+                    .into_iter()
+                    .take(n)
+                    .map(|(pid, name, ticks)| ProcInfo {
+                        pid,
+                        name: if anonymize {
+                            format!("process_{}", pid)
+                        } else {
+                            name.to_string()
+                        },
+                        cpu_pct: 100.0 * ticks as f64 * cpus.max(1) as f64 / diff_total as f64,
+                        mem_kb: RealProcFs
+                            .read_file(&format!("{}/status", pid))
+                            .map(|contents| {
+                                procfs::get_self_memory_from_reader(contents.as_bytes()).0
+                            })
+                            .unwrap_or(0),
+                    })
+                    .collect::<Vec<_>>();
+                if let Ok(mut o) = G_TOP_PROCS.lock() {
+                    *o = top;
+                }
             }
-            (0.0, 0.0, 0.0)
+            pre_total = total;
+            pre_times = times;
+
+            thread::sleep(Duration::from_millis(SAMPLE_PERIOD));
+        }
+    });
+}
+
+static SYS_CPU_DIR: &str = "/sys/devices/system/cpu";
+// cpu0, cpu1, ...
+fn sys_cpu_dirs() -> Vec<std::path::PathBuf> {
+    fs::read_dir(SYS_CPU_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    name.len() > 3
+                        && name.starts_with("cpu")
+                        && name[3..].chars().all(|c| c.is_ascii_digit())
+                })
+                .map(|entry| entry.path())
+                .collect()
         })
-        .unwrap()
+        .unwrap_or_default()
 }
 
-static MEMORY_REGEX: &str = r#"^(?P<key>\S*):\s*(?P<value>\d*)\s*kB"#;
-lazy_static! {
-    static ref MEMORY_REGEX_RE: Regex = Regex::new(MEMORY_REGEX).unwrap();
-}
-pub fn get_memory() -> (u64, u64, u64, u64) {
-    let file = File::open("/proc/meminfo").unwrap();
-    let buf_reader = BufReader::new(file);
-    let mut res_dict = HashMap::new();
-    for line in buf_reader.lines() {
-        let l = line.unwrap();
-        if let Some(caps) = MEMORY_REGEX_RE.captures(&l) {
-            res_dict.insert(
-                caps["key"].to_string(),
-                caps["value"].parse::<u64>().unwrap(),
-            );
-        };
+// (avg MHz, governor)
+pub fn get_cpu_freq() -> (u64, String) {
+    let (mut total_khz, mut cnt) = (0_u64, 0_u64);
+    let mut governor = String::new();
+    for cpu_dir in sys_cpu_dirs() {
+        let cpufreq = cpu_dir.join("cpufreq");
+        if let Ok(Ok(khz)) =
+            fs::read_to_string(cpufreq.join("scaling_cur_freq")).map(|s| s.trim().parse::<u64>())
+        {
+            total_khz += khz;
+            cnt += 1;
+        }
+        if governor.is_empty() {
+            if let Ok(s) = fs::read_to_string(cpufreq.join("scaling_governor")) {
+                governor = s.trim().to_string();
+            }
+        }
+    }
+    if cnt == 0 {
+        return (0, governor);
     }
+    (total_khz / cnt / 1000, governor)
+}
 
-    let mem_total = res_dict["MemTotal"];
-    let swap_total = res_dict["SwapTotal"];
-    let swap_free = res_dict["SwapFree"];
+// (max per core MHz, turbo active), active when a core runs above base clock,
+// intel_pstate base_frequency, else cpuinfo_max_freq which may already include turbo
+pub fn get_cpu_max_freq() -> (u64, bool) {
+    let read_khz = |path: PathBuf| {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+    };
+    let (mut max_khz, mut base_khz) = (0_u64, 0_u64);
+    for cpu_dir in sys_cpu_dirs() {
+        let cpufreq = cpu_dir.join("cpufreq");
+        if let Some(khz) = read_khz(cpufreq.join("scaling_cur_freq")) {
+            max_khz = max_khz.max(khz);
+        }
+        if base_khz == 0 {
+            base_khz = read_khz(cpufreq.join("base_frequency"))
+                .or_else(|| read_khz(cpufreq.join("cpuinfo_max_freq")))
+                .unwrap_or(0);
+        }
+    }
+    (max_khz / 1000, base_khz > 0 && max_khz > base_khz)
+}
 
-    let mem_used = mem_total
-        - res_dict["MemFree"]
-        - res_dict["Buffers"]
-        - res_dict["Cached"]
-        - res_dict["SReclaimable"];
+static INTEL_NO_TURBO_PATH: &str = "/sys/devices/system/cpu/intel_pstate/no_turbo";
+// acpi-cpufreq and amd-pstate
+static CPUFREQ_BOOST_PATH: &str = "/sys/devices/system/cpu/cpufreq/boost";
+static POWERSAVE_WARNED: AtomicBool = AtomicBool::new(false);
 
-    (mem_total, mem_used, swap_total, swap_free)
+// None when the driver exposes neither knob, eg: vms
+pub fn get_turbo_enabled() -> Option<bool> {
+    let read = |path| {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|s| s.trim().parse::<u8>().ok())
+    };
+    read(INTEL_NO_TURBO_PATH)
+        .map(|no_turbo| no_turbo == 0)
+        .or_else(|| read(CPUFREQ_BOOST_PATH).map(|boost| boost == 1))
 }
 
-static IFACE_IGNORE_VEC: &[&str] = &["lo", "docker", "vnet", "veth", "vmbr", "kube", "br-"];
-pub fn get_vnstat_traffic() -> (u64, u64, u64, u64) {
-    let local_now = Local::now();
-    let (mut network_in, mut network_out, mut m_network_in, mut m_network_out) = (0, 0, 0, 0);
-    let a = Command::new("/usr/bin/vnstat")
-        .args(&["--json", "m"])
-        .output()
-        .expect("failed to execute vnstat")
-        .stdout;
-    let b = str::from_utf8(&a).unwrap();
-    let j: HashMap<&str, serde_json::Value> = serde_json::from_str(b).unwrap();
-    for iface in j["interfaces"].as_array().unwrap() {
-        let name = iface["name"].as_str().unwrap();
-        if IFACE_IGNORE_VEC.iter().any(|sk| name.contains(*sk)) {
-            continue;
+// (devices, zram used bytes, zram compression ratio)
+pub fn get_swap_devices<P: ProcFs>(fs: &P) -> (Vec<SwapDevice>, u64, f64) {
+    let swaps = fs
+        .read_file("swaps")
+        .map(|contents| procfs::get_swaps_from_reader(contents.as_bytes()))
+        .unwrap_or_default();
+
+    let (mut zram_used, mut orig, mut compr) = (0, 0, 0);
+    for swap in swaps.iter() {
+        let dev = match swap.name.strip_prefix("/dev/") {
+            Some(dev) if dev.starts_with("zram") => dev,
+            _ => continue,
+        };
+        zram_used += swap.used_kb * KIB;
+        if let Some((o, c)) = fs::read_to_string(format!("/sys/block/{}/mm_stat", dev))
+            .ok()
+            .and_then(|contents| procfs::get_zram_mm_stat(&contents))
+        {
+            orig += o;
+            compr += c;
         }
-        let total_o = iface["traffic"]["total"].as_object().unwrap();
-        let month_v = iface["traffic"]["month"].as_array().unwrap();
-        network_in += total_o["rx"].as_u64().unwrap();
-        network_out += total_o["tx"].as_u64().unwrap();
+    }
+    let ratio = if compr > 0 {
+        orig as f64 / compr as f64
+    } else {
+        0.0
+    };
 
-        for data in month_v {
-            let year = data["date"]["year"].as_i64().unwrap() as i32;
-            let month = data["date"]["month"].as_i64().unwrap() as u32;
-            if local_now.year() != year || local_now.month() != month {
+    let devices = swaps
+        .into_iter()
+        .map(|swap| SwapDevice {
+            name: swap.name,
+            swap_type: swap.swap_type,
+            size_kb: swap.size_kb,
+            used_kb: swap.used_kb,
+            priority: swap.priority,
+        })
+        .collect();
+    (devices, zram_used, ratio)
+}
+
+static SYS_NODE_DIR: &str = "/sys/devices/system/node";
+// KiB, one entry per numa node, empty on non-numa kernels
+pub fn get_numa_memory() -> Vec<NumaMemory> {
+    let mut nodes = fs::read_dir(SYS_NODE_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    let node_id = name.strip_prefix("node")?.parse::<u32>().ok()?;
+                    let contents = fs::read_to_string(entry.path().join("meminfo")).ok()?;
+                    let (total_kb, free_kb) =
+                        procfs::get_numa_meminfo_from_reader(contents.as_bytes());
+                    Some(NumaMemory {
+                        node_id,
+                        total_kb,
+                        free_kb,
+                        used_kb: total_kb.saturating_sub(free_kb),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    nodes.sort_by_key(|n| n.node_id);
+    nodes
+}
+
+#[cfg(unix)]
+pub fn get_iface_addresses() -> Vec<IfaceAddr> {
+    use nix::ifaddrs::getifaddrs;
+    use nix::net::if_::InterfaceFlags;
+    use std::net::Ipv4Addr;
+
+    let mut addrs: BTreeMap<String, IfaceAddr> = BTreeMap::new();
+    if let Ok(ifaddrs) = getifaddrs() {
+        for ifaddr in ifaddrs {
+            if ifaddr.flags.contains(InterfaceFlags::IFF_LOOPBACK) {
                 continue;
             }
-
-            m_network_in += data["rx"].as_u64().unwrap();
-            m_network_out += data["tx"].as_u64().unwrap();
+            let o = addrs
+                .entry(ifaddr.interface_name.to_string())
+                .or_insert_with(|| IfaceAddr {
+                    name: ifaddr.interface_name.to_string(),
+                    ..Default::default()
+                });
+            if let Some(addr) = ifaddr.address {
+                if let Some(sin) = addr.as_sockaddr_in() {
+                    o.ipv4.push(Ipv4Addr::from(sin.ip()).to_string());
+                } else if let Some(sin6) = addr.as_sockaddr_in6() {
+                    o.ipv6.push(sin6.ip().to_string());
+                }
+            }
         }
     }
 
-    (network_in, network_out, m_network_in, m_network_out)
+    addrs
+        .into_values()
+        .filter(|o| !o.ipv4.is_empty() || !o.ipv6.is_empty())
+        .collect()
 }
 
-static TRAFFIC_REGEX: &str = r#"([^\s]+):[\s]{0,}(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)"#;
+const DNS_CACHE_TTL: Duration = Duration::from_secs(300);
 lazy_static! {
-    static ref TRAFFIC_REGEX_RE: Regex = Regex::new(TRAFFIC_REGEX).unwrap();
-}
-pub fn get_sys_traffic() -> (u64, u64) {
-    let (mut network_in, mut network_out) = (0, 0);
-    let file = File::open("/proc/net/dev").unwrap();
-    let buf_reader = BufReader::new(file);
-    for line in buf_reader.lines() {
-        let l = line.unwrap();
-
-        TRAFFIC_REGEX_RE.captures(&l).and_then(|caps| {
-            // println!("caps[0]=>{:?}", caps.get(0).unwrap().as_str());
-            let name = caps.get(1).unwrap().as_str();
-            if IFACE_IGNORE_VEC.iter().any(|sk| name.contains(*sk)) {
-                return None;
+    pub static ref G_DNS_CACHE: Arc<Mutex<HashMap<String, (SocketAddr, Instant)>>> =
+        Arc::new(Default::default());
+}
+
+// fresh cache hit skips dns, a stale entry is still used when dns fails
+fn resolve_cached(probe_addr: &str, want_ipv6: bool) -> Option<SocketAddr> {
+    let cached = G_DNS_CACHE
+        .lock()
+        .ok()
+        .and_then(|o| o.get(probe_addr).copied());
+    if let Some((addr, ts)) = cached {
+        if ts.elapsed() < DNS_CACHE_TTL {
+            return Some(addr);
+        }
+    }
+
+    match probe_addr.to_socket_addrs() {
+        Ok(mut iter) => {
+            // dual-stack dns may hand back an ipv4 addr for an ipv6 name
+            let addr = iter.find(|addr| addr.is_ipv6() == want_ipv6)?;
+            if let Ok(mut o) = G_DNS_CACHE.lock() {
+                o.insert(probe_addr.to_string(), (addr, Instant::now()));
             }
-            let net_in = caps.get(2).unwrap().as_str().parse::<u64>().unwrap();
-            let net_out = caps.get(10).unwrap().as_str().parse::<u64>().unwrap();
+            Some(addr)
+        }
+        Err(err) => {
+            let (addr, _) = cached?;
+            warn!("resolve {} => {:?}, use cached {}", probe_addr, err, addr);
+            Some(addr)
+        }
+    }
+}
 
-            network_in += net_in;
-            network_out += net_out;
-            Some(())
+fn probe_tcp(probe_addr: &str, want_ipv6: bool) -> bool {
+    let mut online = false;
+    if let Some(addr) = resolve_cached(probe_addr, want_ipv6) {
+        info!("{} => {}", probe_addr, addr);
+
+        let r = TcpStream::connect_timeout(&addr, Duration::from_millis(TIMEOUT_MS)).map(|s| {
+            online = true;
+            s.shutdown(Shutdown::Both)
         });
+
+        info!("{:?}", r);
+    }
+    online
+}
+
+// ipv6 is probed by literal addr, the dns based probe is optional and only adds to it
+pub fn get_network(ipv6_literal_probe: &str, ipv6_dns_probe: bool) -> (bool, bool) {
+    let ipv4 = probe_tcp(IPV4_ADDR, false);
+    let mut ipv6 = probe_tcp(ipv6_literal_probe, true);
+    if ipv6_dns_probe {
+        let dns_ipv6 = probe_tcp(IPV6_ADDR, true);
+        if ipv6 != dns_ipv6 {
+            warn!("ipv6 literal probe => {}, dns probe => {}", ipv6, dns_ipv6);
+        }
+        ipv6 |= dns_ipv6;
     }
 
-    (network_in, network_out)
+    (ipv4, ipv6)
 }
 
-static DF_CMD:&str = "df -Tlm --total -t ext4 -t ext3 -t ext2 -t reiserfs -t jfs -t ntfs -t fat32 -t btrfs -t fuseblk -t zfs -t simfs -t xfs";
-pub fn get_hdd() -> (u64, u64) {
-    let (mut hdd_total, mut hdd_used) = (0, 0);
-    let a = &Command::new("/bin/sh")
-        .args(&["-c", DF_CMD])
-        .output()
-        .expect("failed to execute df")
-        .stdout;
-    let _ = str::from_utf8(a).map(|s| {
-        s.trim().split('\n').last().map(|s| {
-            let vec: Vec<&str> = s.split_whitespace().collect();
-            // dbg!(&vec);
-            hdd_total = vec[2].parse::<u64>().unwrap();
-            hdd_used = vec[3].parse::<u64>().unwrap();
-            Some(())
+fn profile_collector<F: FnMut()>(name: &str, rounds: usize, mut f: F) {
+    let mut costs = (0..rounds)
+        .map(|_| {
+            let t = Instant::now();
+            f();
+            t.elapsed().as_secs_f64() * 1000.0
+        })
+        .collect::<Vec<f64>>();
+    costs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    eprintln!(
+        "{:<16} min: {:>10.3}ms  median: {:>10.3}ms  max: {:>10.3}ms",
+        name,
+        costs[0],
+        costs[costs.len() / 2],
+        costs[costs.len() - 1]
+    );
+}
+
+pub fn profile(args: &Args) {
+    let rounds = args.profile_rounds.max(1);
+    eprintln!("profile collectors, {} rounds", rounds);
+
+    // open + read + close per sample vs a kept fd + pread
+    for name in ["stat", "net/dev"] {
+        profile_collector(&format!("{} open", name), rounds, || {
+            let _ = RealProcFs.read_file(name);
         });
+        if let Ok(mut f) = procfs::ProcFile::open(procfs::proc_path(name)) {
+            profile_collector(&format!("{} pread", name), rounds, || {
+                let _ = f.read();
+            });
+        }
+    }
+    profile_collector("get_uptime", rounds, || {
+        get_uptime(&RealProcFs);
     });
-
-    (hdd_total, hdd_used)
+    profile_collector("get_loadavg", rounds, || {
+        get_loadavg(&RealProcFs);
+    });
+    profile_collector("get_memory", rounds, || {
+        get_memory(&RealProcFs);
+    });
+    profile_collector("get_memory_dirty", rounds, || {
+        get_memory_dirty(&RealProcFs);
+    });
+    profile_collector("get_iface_links", rounds, || {
+        get_iface_links();
+    });
+    profile_collector("get_disk_queues", rounds, || {
+        get_disk_queues();
+    });
+    profile_collector("get_swap_devices", rounds, || {
+        get_swap_devices(&RealProcFs);
+    });
+    profile_collector("get_memory_commit", rounds, || {
+        get_memory_commit(&RealProcFs);
+    });
+    profile_collector("get_zfs_arc", rounds, || {
+        get_zfs_arc(&RealProcFs);
+    });
+    profile_collector("get_hdd", rounds, || {
+        hdd::get_hdd();
+    });
+    #[cfg(unix)]
+    profile_collector("get_hdd_statfs", rounds, || {
+        hdd::get_hdd_statfs();
+    });
+    #[cfg(unix)]
+    profile_collector("get_hdd_root", rounds, || {
+        let _ = hdd::try_get_hdd_root();
+    });
+    profile_collector("get_net_errors", rounds, || {
+        get_net_errors(&RealProcFs);
+    });
+    profile_collector("get_softnet_stats", rounds, || {
+        get_softnet_stats(&RealProcFs);
+    });
+    profile_collector("get_effective_memory_limit", rounds, || {
+        get_effective_memory_limit(&RealProcFs);
+    });
+    if args.systemd {
+        profile_collector("get_failed_units", rounds, || {
+            get_failed_units();
+        });
+    }
+    profile_collector("get_entropy_available", rounds, || {
+        get_entropy_available(&RealProcFs);
+    });
+    profile_collector("get_tcp_listen_backlog", rounds, || {
+        get_tcp_listen_backlog(&RealProcFs);
+    });
+    profile_collector("get_top_talkers", rounds, || {
+        get_top_talkers(&RealProcFs, TOP_TALKERS_MAX);
+    });
+    if args.rpi || is_rpi() {
+        profile_collector("get_rpi_throttled", rounds, || {
+            get_rpi_throttled(!args.seccomp_safe);
+        });
+    }
+    profile_collector("get_rapl_power_watts", rounds, || {
+        get_rapl_power_watts();
+    });
+    profile_collector("get_lockup_events", rounds, || {
+        get_lockup_events();
+    });
+    profile_collector("get_recent_kernel_errors", rounds, || {
+        get_recent_kernel_errors(KERNEL_ERRORS_MAX);
+    });
+    profile_collector("get_kernel_thread_count", rounds, || {
+        get_kernel_thread_count(&RealProcFs);
+    });
+    profile_collector("get_reboot_required", rounds, || {
+        get_reboot_required(&RealProcFs);
+    });
+    profile_collector("get_numa_memory", rounds, || {
+        get_numa_memory();
+    });
+    profile_collector("get_readonly_mounts", rounds, || {
+        hdd::get_readonly_mounts(&RealProcFs);
+    });
+    profile_collector("get_sys_traffic", rounds, || {
+        get_sys_traffic(&RealProcFs);
+    });
+    profile_collector("get_sys_packets", rounds, || {
+        get_sys_packets(&RealProcFs);
+    });
+    if args.vnstat {
+        profile_collector("get_vnstat_traffic", rounds, || {
+            get_vnstat_traffic(&args.vnstat_path);
+        });
+    }
 }
 
-#[derive(Debug, Default)]
-pub struct NetSpeed {
-    pub diff: f64,
-    pub clock: f64,
-    pub netrx: u64,
-    pub nettx: u64,
-    pub avgrx: u64,
-    pub avgtx: u64,
+// "critical" over crit, "warn" over warn, empty below both
+pub fn load_alert_level(load_1: f64, warn_at: f64, crit_at: f64) -> &'static str {
+    if load_1 > crit_at {
+        "critical"
+    } else if load_1 > warn_at {
+        "warn"
+    } else {
+        ""
+    }
 }
 
 lazy_static! {
-    pub static ref G_NET_SPEED: Arc<Mutex<NetSpeed>> = Arc::new(Default::default());
+    static ref G_LOAD_ALERT: Mutex<&'static str> = Mutex::new("");
 }
 
-#[allow(unused)]
-pub fn start_net_speed_collect_t() {
-    thread::spawn(|| loop {
-        let _ = File::open("/proc/net/dev").map(|file| {
-            let buf_reader = BufReader::new(file);
-            let (mut avgrx, mut avgtx) = (0, 0);
-            for line in buf_reader.lines() {
-                let l = line.unwrap();
-                let v: Vec<&str> = l.split(':').collect();
-                if v.len() < 2 {
-                    continue;
+pub fn sample(args: &Args, stat: &mut StatRequest) {
+    stat.version = env!("CARGO_PKG_VERSION").to_string();
+    stat.vnstat = traffic_source(args) == TrafficSource::Vnstat;
+
+    let mut collector_status = HashMap::new();
+
+    if args.collect_enabled("uptime") {
+        let uptime = try_get_uptime(&RealProcFs);
+        collector_status.insert("uptime", uptime.is_ok());
+        stat.uptime = uptime.unwrap_or(0);
+        stat.boot_time = get_boot_time();
+    }
+
+    if args.collect_enabled("load") {
+        let loadavg = try_get_loadavg(&RealProcFs);
+        let load_ok = loadavg.is_ok();
+        collector_status.insert("load", load_ok);
+        let (load_1, load_5, load_15) = loadavg.unwrap_or_default();
+        stat.load_1 = load_1;
+        stat.load_5 = load_5;
+        stat.load_15 = load_15;
+        if load_ok {
+            let (warn_at, crit_at) = args.load_thresholds();
+            let level = load_alert_level(load_1, warn_at, crit_at);
+            // logged when the level changes, not on every sample
+            if let Ok(mut prev) = G_LOAD_ALERT.lock() {
+                if *prev != level {
+                    match level {
+                        "critical" => error!(
+                            "Load average 1m {:.2} exceeds critical threshold {:.2}",
+                            load_1, crit_at
+                        ),
+                        "warn" => warn!(
+                            "Load average 1m {:.2} exceeds warning threshold {:.2}",
+                            load_1, warn_at
+                        ),
+                        _ => info!("Load average 1m {:.2} back under {:.2}", load_1, warn_at),
+                    }
+                    *prev = level;
                 }
+            }
+            stat.load_alert = level.to_string();
+        }
+        if args.load_window_secs > 0 {
+            if let Ok(o) = G_LOAD_CUSTOM.lock() {
+                stat.load_custom = *o;
+            }
+        }
+    }
 
-                if IFACE_IGNORE_VEC.iter().any(|sk| v[0].contains(*sk)) {
-                    continue;
+    if args.collect_enabled("mem") {
+        let memory = try_get_memory(&RealProcFs);
+        collector_status.insert("mem", memory.is_ok());
+        let (mem_total, mem_used, swap_total, swap_free) = memory.unwrap_or_default();
+        stat.memory_total = mem_total;
+        stat.memory_used = mem_used;
+        stat.swap_total = swap_total;
+        stat.swap_used = swap_total.saturating_sub(swap_free);
+
+        if is_in_container() {
+            if let Some((limit, current)) = get_cgroup_memory() {
+                if limit < stat.memory_total {
+                    stat.memory_total = limit;
+                    stat.memory_used = current.min(limit);
                 }
-                let v1: Vec<&str> = v[1].split_whitespace().collect();
-                avgrx += v1[0].parse::<u64>().unwrap();
-                avgtx += v1[8].parse::<u64>().unwrap();
             }
+        }
 
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as f64;
+        stat.effective_memory_limit = get_effective_memory_limit(&RealProcFs);
 
-            if let Ok(mut t) = G_NET_SPEED.lock() {
-                t.diff = now - t.clock;
-                t.clock = now;
-                t.netrx = ((avgrx - t.avgrx) as f64 / t.diff) as u64;
-                t.nettx = ((avgtx - t.avgtx) as f64 / t.diff) as u64;
-                t.avgrx = avgrx;
-                t.avgtx = avgtx;
+        let (mem_dirty_kb, mem_writeback_kb) = get_memory_dirty(&RealProcFs);
+        stat.mem_dirty_kb = mem_dirty_kb;
+        stat.mem_writeback_kb = mem_writeback_kb;
 
-                // dbg!(&t);
-            }
-        });
-        thread::sleep(Duration::from_millis(SAMPLE_PERIOD));
-    });
-}
+        let (swap_devices, zram_swap_used, zram_compression_ratio) = get_swap_devices(&RealProcFs);
+        stat.swap_devices = swap_devices;
+        stat.zram_swap_used = zram_swap_used;
+        stat.zram_compression_ratio = zram_compression_ratio;
 
-lazy_static! {
-    pub static ref G_CPU_PERCENT: Arc<Mutex<f64>> = Arc::new(Default::default());
-}
-#[allow(unused)]
-pub fn start_cpu_percent_collect_t() {
-    let mut pre_cpu: Vec<u64> = vec![0, 0, 0, 0];
-    thread::spawn(move || loop {
-        let _ = File::open("/proc/stat").map(|file| {
-            let mut buf_reader = BufReader::new(file);
-            let mut buf = String::new();
-            let _ = buf_reader.read_line(&mut buf).map(|_| {
-                let cur_cpu = buf
-                    .split_whitespace()
-                    .enumerate()
-                    .filter(|&(idx, _)| idx > 0 && idx < 5)
-                    .map(|(_, e)| e.parse::<u64>().unwrap())
-                    .collect::<Vec<_>>();
+        let (mem_committed_kb, mem_commit_limit_kb) = get_memory_commit(&RealProcFs);
+        stat.mem_committed_kb = mem_committed_kb;
+        stat.mem_commit_limit_kb = mem_commit_limit_kb;
+        if mem_commit_limit_kb > 0 {
+            stat.commit_ratio = mem_committed_kb as f64 / mem_commit_limit_kb as f64;
+        }
 
-                let pre: u64 = pre_cpu.iter().sum();
-                let cur: u64 = cur_cpu.iter().sum();
-                let mut st = cur - pre;
-                if st == 0 {
-                    st = 1;
-                }
+        stat.memory_hugepages_kb = RealProcFs
+            .read_meminfo()
+            .map(|contents| procfs::get_hugepages_from_reader(contents.as_bytes()))
+            .unwrap_or(0);
 
-                let res = 100.0 - (100.0 * (cur_cpu[3] - pre_cpu[3]) as f64 / st as f64);
+        let (thp_mode, thp_anon_kb) = get_thp(&RealProcFs);
+        stat.thp_mode = thp_mode;
+        stat.thp_anon_kb = thp_anon_kb;
 
-                // dbg!(&pre_cpu);
-                // dbg!(&cur_cpu);
+        let (arc_size, arc_max) = get_zfs_arc(&RealProcFs);
+        stat.zfs_arc_size = arc_size;
+        stat.zfs_arc_max = arc_max;
+        if args.zfs_arc_cache {
+            stat.memory_used = stat.memory_used.saturating_sub(arc_size);
+        }
+    }
 
-                pre_cpu = cur_cpu;
+    stat.in_container = is_in_container();
+    stat.container_runtime = G_CONTAINER_INFO.0.to_string();
+    stat.container_id = G_CONTAINER_INFO.1.to_string();
 
-                if let Ok(mut cpu_percent) = G_CPU_PERCENT.lock() {
-                    *cpu_percent = res.round();
-                    // dbg!(cpu_percent);
-                }
-            });
-        });
+    let (timezone, utc_offset_sec) = get_timezone();
+    stat.timezone = timezone;
+    stat.utc_offset_sec = utc_offset_sec;
 
-        thread::sleep(Duration::from_millis(SAMPLE_PERIOD));
-    });
-}
+    let (agent_rss_kb, agent_vms_kb) = get_self_memory_usage(&RealProcFs);
+    stat.agent_rss_kb = agent_rss_kb;
+    stat.agent_vms_kb = agent_vms_kb;
+    if agent_rss_kb > args.agent_max_rss_mb * 1024 {
+        warn!(
+            "agent rss {}KiB exceeds limit {}MiB",
+            agent_rss_kb, args.agent_max_rss_mb
+        );
+    }
+
+    if args.collect_enabled("hdd") {
+        #[cfg(unix)]
+        let (hdd_total, hdd_used) = if args.hdd_mount == ["/"] {
+            let total = hdd::try_get_hdd_root();
+            collector_status.insert("hdd", total.is_ok());
+            total.unwrap_or_default()
+        } else if args.hdd_statfs && args.hdd_mount.is_empty() {
+            let total = hdd::get_hdd_statfs();
+            collector_status.insert("hdd", total.0 > 0);
+            total
+        } else {
+            let total = hdd::try_get_hdd_mounts(&args.hdd_mount);
+            collector_status.insert("hdd", total.is_ok());
+            total.unwrap_or_default()
+        };
+        #[cfg(not(unix))]
+        let (hdd_total, hdd_used) = {
+            let total = hdd::try_get_hdd_mounts(&args.hdd_mount);
+            collector_status.insert("hdd", total.is_ok());
+            total.unwrap_or_default()
+        };
+        stat.hdd_total = hdd_total;
+        stat.hdd_used = hdd_used;
+
+        if args.probe_disk_latency.is_some() {
+            stat.disk_probe_latency_ms = hdd::G_DISK_PROBE_LATENCY_MS.load(Ordering::Relaxed);
+        }
 
-pub fn get_network() -> (bool, bool) {
-    let mut network: [bool; 2] = [false, false];
-    let addrs = vec![IPV4_ADDR, IPV6_ADDR];
-    for (idx, probe_addr) in addrs.into_iter().enumerate() {
-        let _ = probe_addr.to_socket_addrs().map(|mut iter| {
-            if let Some(addr) = iter.next() {
-                info!("{} => {}", probe_addr, addr);
+        stat.readonly_mounts = hdd::get_readonly_mounts(&RealProcFs);
+        if !stat.readonly_mounts.is_empty() {
+            warn!("read-only mounts => {:?}", stat.readonly_mounts);
+        }
+
+        #[cfg(unix)]
+        if !args.watch_partition.is_empty() {
+            let mounts = args
+                .watch_partition
+                .iter()
+                .map(|(path, _)| path.to_string())
+                .collect::<Vec<_>>();
+            for usage in hdd::get_partition_usage(&mounts) {
+                let threshold = args
+                    .watch_partition
+                    .iter()
+                    .find(|(path, _)| usage.mount_point.eq(path))
+                    .map_or(100.0, |&(_, threshold)| threshold);
+                if usage.use_pct > threshold {
+                    warn!(
+                        "partition {} ({}) use {:.1}% ({}/{} MiB) exceeds {}%",
+                        usage.mount_point,
+                        usage.device,
+                        usage.use_pct,
+                        usage.used_mb,
+                        usage.total_mb,
+                        threshold
+                    );
+                    stat.critical_partitions.push(usage.mount_point);
+                }
+            }
+        }
+    }
 
-                let r =
-                    TcpStream::connect_timeout(&addr, Duration::from_millis(TIMEOUT_MS)).map(|s| {
-                        network[idx] = true;
-                        s.shutdown(Shutdown::Both)
-                    });
+    if args.collect_enabled("traffic") {
+        let net_fs = NetNsProcFs(args.net_ns());
+        match traffic_source(args) {
+            TrafficSource::Nft => {
+                let (network_in, network_out) = get_nft_traffic(&args.traffic_nft);
+                collector_status.insert("traffic", network_in > 0 || network_out > 0);
+                stat.network_in = network_in;
+                stat.network_out = network_out;
+            }
+            TrafficSource::Vnstat => {
+                let (network_in, network_out, m_network_in, m_network_out) =
+                    get_vnstat_traffic(&args.vnstat_path);
+                collector_status.insert("traffic", true);
+                stat.network_in = network_in;
+                stat.network_out = network_out;
+                stat.last_network_in = network_in - m_network_in;
+                stat.last_network_out = network_out - m_network_out;
+            }
+            TrafficSource::Proc => {
+                let traffic = try_get_sys_traffic(&net_fs);
+                collector_status.insert("traffic", traffic.is_ok());
+                let (network_in, network_out) = traffic.unwrap_or_default();
+                stat.network_in = network_in;
+                stat.network_out = network_out;
+            }
+        }
 
-                info!("{:?}", r);
+        let (rx_packets, tx_packets) = get_sys_packets(&net_fs);
+        stat.network_rx_packets = rx_packets;
+        stat.network_tx_packets = tx_packets;
+        if rx_packets > 0 {
+            // same counter set as the packets, not vnstat or nft totals
+            let rx_bytes = if traffic_source(args) == TrafficSource::Proc {
+                stat.network_in
+            } else {
+                get_sys_traffic(&net_fs).0
             };
-        });
+            stat.avg_rx_packet_size_bytes = rx_bytes as f64 / rx_packets as f64;
+        }
+
+        let (rx_errors, tx_errors, rx_dropped, tx_dropped) = get_net_errors(&net_fs);
+        stat.net_rx_errors = rx_errors;
+        stat.net_tx_errors = tx_errors;
+        stat.net_rx_dropped = rx_dropped;
+        stat.net_tx_dropped = tx_dropped;
+
+        let softnet = get_softnet_stats(&net_fs);
+        stat.softnet_dropped = softnet.total_dropped;
+        stat.softnet_time_squeeze = softnet.total_time_squeeze;
+        stat.softnet_cpu_collision = softnet.total_cpu_collision;
+        if softnet.total_dropped > 0 && !SOFTNET_DROPPED_WARNED.swap(true, Ordering::Relaxed) {
+            warn!(
+                "softnet dropped {} packets, rx backlog overflowing",
+                softnet.total_dropped
+            );
+        }
+        if softnet.total_time_squeeze > 0 && !SOFTNET_SQUEEZE_WARNED.swap(true, Ordering::Relaxed) {
+            warn!(
+                "softnet time_squeeze {}, consider raising net.core.netdev_budget",
+                softnet.total_time_squeeze
+            );
+        }
     }
 
-    (network[0], network[1])
-}
+    if args.collect_enabled("cpu") {
+        if let Ok(o) = G_CPU_PERCENT.lock() {
+            stat.cpu = *o;
+        }
+        if let Ok(o) = G_CPU_STEAL.lock() {
+            stat.cpu_steal_pct = o.0;
+            stat.cpu_contended = o.1;
+        }
+        if let Ok(o) = G_CPU_SOFTIRQ.lock() {
+            stat.cpu_softirq = *o;
+        }
+        if let Ok(o) = G_CPU_IOWAIT.lock() {
+            stat.cpu_iowait = *o;
+        }
+        if let Ok(o) = G_CPU_NICE.lock() {
+            stat.cpu_nice = *o;
+        }
+        if args.top_procs > 0 {
+            if let Ok(o) = G_TOP_PROCS.lock() {
+                stat.top_procs = o.clone();
+            }
+        }
 
-pub fn sample(args: &Args, stat: &mut StatRequest) {
-    stat.version = env!("CARGO_PKG_VERSION").to_string();
-    stat.vnstat = args.vnstat;
+        let (cpu_model, cpu_cores_physical, cpu_cores_logical) = get_cpu_topology();
+        stat.cpu_model = cpu_model;
+        stat.cpu_cores_physical = cpu_cores_physical;
+        stat.cpu_cores_logical = cpu_cores_logical;
+        if is_in_container() {
+            if let Some(cpus) = get_cgroup_cpus() {
+                stat.cpu_cores_logical = stat.cpu_cores_logical.min(cpus);
+            }
+        }
+
+        let (cpu_freq_mhz, cpu_governor) = get_cpu_freq();
+        stat.cpu_freq_mhz = cpu_freq_mhz;
+        if cpu_governor == "powersave" && !POWERSAVE_WARNED.swap(true, Ordering::Relaxed) {
+            warn!("cpu governor powersave, benchmarks and latency may suffer");
+        }
+        stat.cpu_governor = cpu_governor;
+        stat.turbo_enabled = get_turbo_enabled().unwrap_or(false);
+        let (max_freq_mhz, turbo_active) = get_cpu_max_freq();
+        stat.max_freq_mhz = max_freq_mhz;
+        stat.turbo_active = turbo_active;
+    }
 
-    stat.uptime = get_uptime();
+    if args.collect_enabled("net") {
+        if let Ok(o) = G_NET_SPEED.lock() {
+            stat.network_rx = o.netrx;
+            stat.network_tx = o.nettx;
+            let (rx_pct, tx_pct) = get_net_utilization(&o.iface_speed);
+            stat.rx_utilization_pct = rx_pct;
+            stat.tx_utilization_pct = tx_pct;
+            if args.raw_counters {
+                stat.raw_rx_bytes = o.avgrx;
+                stat.raw_tx_bytes = o.avgtx;
+                stat.raw_counter_ts_ms = o.ts_ms;
+            }
+        }
+        stat.iface_links = get_iface_links();
+    }
 
-    let (load_1, load_5, load_15) = get_loadavg();
-    stat.load_1 = load_1;
-    stat.load_5 = load_5;
-    stat.load_15 = load_15;
+    if args.collect_enabled("irq") {
+        stat.irq_per_sec = G_IRQ_RATE.load(Ordering::Relaxed);
+        stat.softirq_per_sec = G_SOFTIRQ_RATE.load(Ordering::Relaxed);
+        if let Ok(o) = G_SOFTIRQ_NET.lock() {
+            stat.softirq_net_rx_pct = o.0;
+            stat.softirq_net_tx_pct = o.1;
+        }
+    }
 
-    let (mem_total, mem_used, swap_total, swap_free) = get_memory();
-    stat.memory_total = mem_total;
-    stat.memory_used = mem_used;
-    stat.swap_total = swap_total;
-    stat.swap_used = swap_total - swap_free;
+    if args.collect_enabled("conntrack") {
+        let (conntrack_count, conntrack_max) = get_conntrack(&RealProcFs);
+        stat.conntrack_count = conntrack_count;
+        stat.conntrack_max = conntrack_max;
+    }
 
-    let (hdd_total, hdd_used) = get_hdd();
-    stat.hdd_total = hdd_total;
-    stat.hdd_used = hdd_used;
+    // watts since the previous sample, 0 on the first one
+    if args.collect_enabled("power") {
+        stat.cpu_power_watts = get_rapl_power_watts().unwrap_or(0.0);
+    }
 
-    if args.vnstat {
-        let (network_in, network_out, m_network_in, m_network_out) = get_vnstat_traffic();
-        stat.network_in = network_in;
-        stat.network_out = network_out;
-        stat.last_network_in = network_in - m_network_in;
-        stat.last_network_out = network_out - m_network_out;
-    } else {
-        let (network_in, network_out) = get_sys_traffic();
-        stat.network_in = network_in;
-        stat.network_out = network_out;
+    if args.collect_enabled("entropy") {
+        let (entropy_available, entropy_poolsize) = get_entropy_available(&RealProcFs);
+        collector_status.insert("entropy", entropy_poolsize > 0);
+        if entropy_poolsize > 0 && entropy_available < ENTROPY_LOW {
+            warn!(
+                "entropy low => {} of {} bits",
+                entropy_available, entropy_poolsize
+            );
+        }
+        stat.entropy_available = entropy_available;
+        stat.entropy_poolsize = entropy_poolsize;
+    }
+
+    if args.systemd {
+        stat.failed_units = get_failed_units();
+        stat.failed_units_count = stat.failed_units.len() as u32;
+    }
+
+    if args.collect_enabled("tcp") {
+        stat.tcp_listen_backlog_max = get_tcp_listen_backlog(&RealProcFs);
+        if args.top_talkers > 0 {
+            stat.top_talkers = get_top_talkers(&RealProcFs, args.top_talkers);
+        }
+    }
+
+    if args.rpi || is_rpi() {
+        let (throttled, reasons) = get_rpi_throttled(!args.seccomp_safe);
+        if throttled {
+            warn!("raspberry pi throttled => {:?}", reasons);
+        }
+        stat.throttled = throttled;
+        stat.throttle_reasons = reasons;
+    }
+
+    // dmesg is a subprocess
+    if args.collect_enabled("lockup") && !args.seccomp_safe {
+        stat.lockup_events = get_lockup_events();
+        if stat.lockup_events > 0 {
+            error!(
+                "kernel lockup detected => {} new events",
+                stat.lockup_events
+            );
+        }
+    }
+
+    if args.include_dmesg_errors > 0 && !args.seccomp_safe {
+        stat.kernel_errors = get_recent_kernel_errors(args.include_dmesg_errors);
+    }
+
+    if args.collect_enabled("kthreads") {
+        stat.kernel_threads = get_kernel_thread_count(&RealProcFs);
+    }
+
+    if args.collect_enabled("reboot") {
+        stat.reboot_required = get_reboot_required(&RealProcFs);
+        if stat.reboot_required {
+            stat.reboot_required_pkgs = get_reboot_required_pkgs();
+            warn!("reboot required => {:?}", stat.reboot_required_pkgs);
+        }
+    }
+
+    #[cfg(unix)]
+    if args.plugin_dir.is_some() {
+        if let Ok(o) = plugin::G_PLUGIN_METRICS.lock() {
+            stat.custom_metrics = o.clone();
+        }
+    }
+
+    if args.numa_stats {
+        stat.numa_memory = get_numa_memory();
     }
 
-    if let Ok(o) = G_CPU_PERCENT.lock() {
-        stat.cpu = *o;
+    stat.collector_status = collector_status
+        .into_iter()
+        .map(|(name, ok)| (name.to_string(), ok))
+        .collect();
+
+    #[cfg(unix)]
+    if args.report_addresses {
+        stat.iface_addresses = get_iface_addresses();
     }
 
-    if let Ok(o) = G_NET_SPEED.lock() {
-        stat.network_rx = o.netrx;
-        stat.network_tx = o.nettx;
+    if args.collect_enabled("disk_io") {
+        if let Ok(o) = G_DISK_AWAIT.lock() {
+            stat.disk_await_ms = o.clone();
+        }
+        stat.disks = get_disk_queues();
     }
     // {
     //     let o = &*G_PING_10010.get().unwrap().lock().unwrap();
@@ -348,3 +2189,132 @@ pub fn sample(args: &Args, stat: &mut StatRequest) {
     //     stat.time_10086 = o.ping_time.into();
     // }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn vnstat_path_is_invoked() {
+        let now = Local::now();
+        let json = format!(
+            r#"{{"interfaces":[{{"name":"eth0","traffic":{{"total":{{"rx":300,"tx":400}},"month":[{{"date":{{"year":{},"month":{}}},"rx":30,"tx":40}}]}}}},{{"name":"docker0","traffic":{{"total":{{"rx":1,"tx":1}},"month":[]}}}}]}}"#,
+            now.year(),
+            now.month()
+        );
+        let bin = env::temp_dir().join(format!("mock_vnstat_{}", std::process::id()));
+        fs::write(
+            &bin,
+            format!(
+                "#!/bin/sh\n[ \"$1\" = \"--json\" ] && [ \"$2\" = \"m\" ] || exit 1\necho '{}'\n",
+                json
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&bin, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(resolve_vnstat_path(&bin), bin);
+        assert_eq!(get_vnstat_traffic(&bin), (300, 400, 30, 40));
+
+        let _ = fs::remove_file(&bin);
+    }
+
+    #[test]
+    fn load_alert_levels() {
+        assert_eq!(load_alert_level(0.5, 3.2, 6.0), "");
+        assert_eq!(load_alert_level(3.2, 3.2, 6.0), "");
+        assert_eq!(load_alert_level(3.3, 3.2, 6.0), "warn");
+        assert_eq!(load_alert_level(6.1, 3.2, 6.0), "critical");
+        // crit below warn still wins
+        assert_eq!(load_alert_level(2.0, 3.0, 1.5), "critical");
+    }
+
+    #[test]
+    fn kernel_threads_from_procfs() {
+        let fs = procfs::MockProcFs(HashMap::from([
+            (
+                "1/stat",
+                "1 (systemd) S 0 1 1 0 -1 4194560 0 0 0 0 10 20 0 0 20 0 1 0 1 0 0",
+            ),
+            (
+                "2/stat",
+                "2 (kthreadd) S 0 0 0 0 -1 2129984 0 0 0 0 0 0 0 0 20 0 1 0 1 0 0",
+            ),
+            (
+                "3/stat",
+                "3 (rcu_gp) I 2 0 0 0 -1 69238880 0 0 0 0 0 0 0 0 0 -20 1 0 1 0 0",
+            ),
+            ("3/status", "Name:\trcu_gp\n"),
+            ("meminfo", "MemTotal: 1 kB\n"),
+        ]));
+        assert_eq!(fs.list_pids().unwrap(), vec![1, 2, 3]);
+        assert_eq!(get_kernel_thread_count(&fs), 2);
+    }
+
+    #[test]
+    fn entropy_pool() {
+        let fs = procfs::MockProcFs(HashMap::from([
+            ("sys/kernel/random/entropy_avail", "3754\n"),
+            ("sys/kernel/random/poolsize", "4096\n"),
+        ]));
+        assert_eq!(get_entropy_available(&fs), (3754, 4096));
+        assert_eq!(
+            get_entropy_available(&procfs::MockProcFs(HashMap::new())),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn memory_in_bytes() {
+        let fs = procfs::MockProcFs(HashMap::from([(
+            "meminfo",
+            "MemTotal:        2048000 kB\nMemFree:          512000 kB\nBuffers:           10000 kB\nCached:           100000 kB\nSReclaimable:      50000 kB\nSwapTotal:        1024000 kB\nSwapFree:         1000000 kB\n",
+        )]));
+        assert_eq!(
+            get_memory(&fs),
+            (
+                2_048_000 * 1024,
+                1_376_000 * 1024,
+                1_024_000 * 1024,
+                1_000_000 * 1024
+            )
+        );
+        assert_eq!(get_effective_memory_limit(&fs), 2_048_000 * 1024);
+    }
+
+    #[test]
+    fn memory_excludes_hugepages() {
+        let fs = procfs::MockProcFs(HashMap::from([(
+            "meminfo",
+            "MemTotal:        8192000 kB\nMemFree:         4000000 kB\nCached:           100000 kB\nHugePages_Total:     512\nHugePages_Free:      512\nHugepagesize:       2048 kB\n",
+        )]));
+        let (_, mem_used, _, _) = get_memory(&fs);
+        assert_eq!(
+            mem_used,
+            (8_192_000 - 4_000_000 - 100_000 - 512 * 2048) * 1024
+        );
+    }
+
+    #[test]
+    fn netns_paths() {
+        assert_eq!(parse_netns_name("tenant-a"), Ok("tenant-a".to_string()));
+        for bad in ["", ".", "..", "../proc/1/ns/net", "a/b"] {
+            assert!(parse_netns_name(bad).is_err());
+        }
+        assert_eq!(
+            NetNs::Named("tenant-a".to_string()).path(),
+            Path::new("/var/run/netns/tenant-a")
+        );
+        assert_eq!(NetNs::Pid(1).path(), Path::new("/proc/1/ns/net"));
+    }
+
+    #[test]
+    fn hdd_in_bytes() {
+        let fs = procfs::MockProcFs(HashMap::from([("mounts", "/dev/root / ext4 rw 0 0\n")]));
+        let st = nix::sys::statvfs::statvfs("/").unwrap();
+        let total = st.blocks() as u64 * st.fragment_size() as u64;
+        assert_eq!(hdd::get_hdd_statfs_from(&fs).0, total);
+        assert_eq!(hdd::try_get_hdd_root().unwrap().0, total);
+    }
+}
@@ -104,19 +104,19 @@ pub fn sample(args: &Args, stat: &mut StatRequest) {
     stat.load_5 = load_avg.five;
     stat.load_15 = load_avg.fifteen;
 
-    // mem KB -> KiB
+    // mem KB (1000) -> bytes
     let (mem_total, mem_used, swap_total, swap_free) = (
-        sys.total_memory() * 1000 / 1024,
-        sys.used_memory() * 1000 / 1024,
-        sys.total_swap() * 1000 / 1024,
-        sys.free_swap() * 1000 / 1024,
+        sys.total_memory() * 1000,
+        sys.used_memory() * 1000,
+        sys.total_swap() * 1000,
+        sys.free_swap() * 1000,
     );
     stat.memory_total = mem_total;
     stat.memory_used = mem_used;
     stat.swap_total = swap_total;
-    stat.swap_used = swap_total - swap_free;
+    stat.swap_used = swap_total.saturating_sub(swap_free);
 
-    // hdd  KB -> KiB
+    // hdd bytes
     let (mut hdd_total, mut hdd_avail) = (0_u64, 0_u64);
     for disk in sys.disks() {
         let fs = String::from_utf8_lossy(disk.file_system()).to_lowercase();
@@ -125,12 +125,13 @@ pub fn sample(args: &Args, stat: &mut StatRequest) {
             hdd_avail += disk.available_space();
         }
     }
-    stat.hdd_total = hdd_total / 1024 / 1024;
-    stat.hdd_used = (hdd_total - hdd_avail) / 1024 / 1024;
+    stat.hdd_total = hdd_total;
+    stat.hdd_used = hdd_total - hdd_avail;
 
     // traffic
     if args.vnstat {
-        let (network_in, network_out, m_network_in, m_network_out) = get_vnstat_traffic();
+        let (network_in, network_out, m_network_in, m_network_out) =
+            get_vnstat_traffic(&args.vnstat_path);
         stat.network_in = network_in;
         stat.network_out = network_out;
         stat.last_network_in = network_in - m_network_in;
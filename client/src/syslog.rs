@@ -0,0 +1,122 @@
+// bsd syslog (rfc 3164) over the local /dev/log datagram socket, as glibc syslog(3) sends it
+#![cfg_attr(not(unix), allow(dead_code))]
+use std::io;
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::path::Path;
+
+const SYSLOG_PATH: &str = "/dev/log";
+
+pub const SEV_CRIT: u8 = 2;
+pub const SEV_NOTICE: u8 = 5;
+
+// facility name or number, eg: daemon, local0, 3
+pub fn parse_facility(s: &str) -> Result<u8, String> {
+    let facility = match s {
+        "kern" => 0,
+        "user" => 1,
+        "mail" => 2,
+        "daemon" => 3,
+        "auth" => 4,
+        "syslog" => 5,
+        _ => match s.strip_prefix("local").and_then(|n| n.parse::<u8>().ok()) {
+            Some(n) if n <= 7 => 16 + n,
+            _ => s
+                .parse::<u8>()
+                .ok()
+                .filter(|&n| n <= 23)
+                .ok_or_else(|| format!("invalid syslog facility `{}`", s))?,
+        },
+    };
+    Ok(facility)
+}
+
+// `<PRI>Mmm dd hh:mm:ss tag[pid]: message`, the local daemon adds the hostname
+pub fn format_syslog(
+    facility: u8,
+    severity: u8,
+    tag: &str,
+    pid: u32,
+    ts: &str,
+    message: &str,
+) -> String {
+    format!(
+        "<{}>{} {}[{}]: {}",
+        facility as u16 * 8 + severity as u16,
+        ts,
+        tag,
+        pid,
+        message
+    )
+}
+
+#[cfg(unix)]
+pub fn send_syslog_to<P: AsRef<Path>>(
+    path: P,
+    facility: u8,
+    severity: u8,
+    tag: &str,
+    message: &str,
+) -> io::Result<()> {
+    let ts = chrono::Local::now().format("%b %e %H:%M:%S").to_string();
+    let line = format_syslog(facility, severity, tag, std::process::id(), &ts, message);
+    UnixDatagram::unbound()?.send_to(line.as_bytes(), path)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn send_syslog(facility: u8, severity: u8, tag: &str, message: &str) -> io::Result<()> {
+    send_syslog_to(SYSLOG_PATH, facility, severity, tag, message)
+}
+
+// refused at startup, kept so the callers need no cfg
+#[cfg(not(unix))]
+pub fn send_syslog(_facility: u8, _severity: u8, _tag: &str, _message: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "no /dev/log"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn facility_names() {
+        assert_eq!(parse_facility("daemon"), Ok(3));
+        assert_eq!(parse_facility("local0"), Ok(16));
+        assert_eq!(parse_facility("local7"), Ok(23));
+        assert_eq!(parse_facility("1"), Ok(1));
+        assert!(parse_facility("local8").is_err());
+        assert!(parse_facility("24").is_err());
+        assert!(parse_facility("nope").is_err());
+    }
+
+    #[test]
+    fn bsd_format() {
+        assert_eq!(
+            format_syslog(3, SEV_CRIT, "stat_client", 42, "Oct  6 12:00:00", "mem 96%"),
+            "<26>Oct  6 12:00:00 stat_client[42]: mem 96%"
+        );
+        assert_eq!(
+            format_syslog(23, 7, "t", 1, "Jan 16 01:02:03", "m"),
+            "<191>Jan 16 01:02:03 t[1]: m"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn send_to_socket() {
+        let path = std::env::temp_dir().join(format!("stat_syslog_{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let sock = UnixDatagram::bind(&path).unwrap();
+
+        send_syslog_to(&path, 3, SEV_CRIT, "stat_client", "load 9.1").unwrap();
+        let mut buf = [0_u8; 256];
+        let n = sock.recv(&mut buf).unwrap();
+        let line = std::str::from_utf8(&buf[..n]).unwrap();
+        assert!(line.starts_with("<26>"));
+        assert!(line.ends_with(&format!("stat_client[{}]: load 9.1", std::process::id())));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
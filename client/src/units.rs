@@ -0,0 +1,26 @@
+// collectors work in bytes, memory/swap/hdd go on the wire in bytes with --byte-units,
+// otherwise in the units the dashboards always read:
+// memory KiB, /proc/meminfo `kB` is 1024, and hdd MiB, `df -m` is 1048576
+use stat_common::server_status::StatRequest;
+
+pub const KIB: u64 = 1024;
+pub const MIB: u64 = 1024 * 1024;
+
+// bytes -> KiB / MiB, the report format without byte_units
+pub fn to_legacy_units(stat: &mut StatRequest) {
+    for v in [
+        &mut stat.memory_total,
+        &mut stat.memory_used,
+        &mut stat.swap_total,
+        &mut stat.swap_used,
+        &mut stat.effective_memory_limit,
+        &mut stat.zfs_arc_size,
+        &mut stat.zfs_arc_max,
+        &mut stat.zram_swap_used,
+    ] {
+        *v /= KIB;
+    }
+    stat.hdd_total /= MIB;
+    stat.hdd_used /= MIB;
+    stat.byte_units = false;
+}
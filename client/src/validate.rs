@@ -0,0 +1,280 @@
+// plausibility checks on a sampled report, a parser bug, eg: a wrong column index,
+// gets logged and clamped here instead of drawing nonsense on the server
+use std::fmt;
+
+use stat_common::server_status::StatRequest;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    CpuOutOfRange(f64),
+    MemoryUsedOverTotal { used: u64, total: u64 },
+    SwapUsedOverTotal { used: u64, total: u64 },
+    HddUsedOverTotal { used: u64, total: u64 },
+    // rates, loads and ratios, negative or NaN
+    Negative { field: &'static str, value: f64 },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::CpuOutOfRange(cpu) => write!(f, "cpu {} not in [0, 100]", cpu),
+            ValidationError::MemoryUsedOverTotal { used, total } => {
+                write!(f, "memory_used {} > memory_total {}", used, total)
+            }
+            ValidationError::SwapUsedOverTotal { used, total } => {
+                write!(f, "swap_used {} > swap_total {}", used, total)
+            }
+            ValidationError::HddUsedOverTotal { used, total } => {
+                write!(f, "hdd_used {} > hdd_total {}", used, total)
+            }
+            ValidationError::Negative { field, value } => write!(f, "{} {} < 0", field, value),
+        }
+    }
+}
+
+// byte counters and speeds are unsigned, only the f64 ones can go negative
+fn signed_fields(stat: &StatRequest) -> [(&'static str, f64); 14] {
+    [
+        ("load_1", stat.load_1),
+        ("load_5", stat.load_5),
+        ("load_15", stat.load_15),
+        ("load_custom", stat.load_custom),
+        ("rx_utilization_pct", stat.rx_utilization_pct),
+        ("tx_utilization_pct", stat.tx_utilization_pct),
+        ("avg_rx_packet_size_bytes", stat.avg_rx_packet_size_bytes),
+        ("commit_ratio", stat.commit_ratio),
+        ("zram_compression_ratio", stat.zram_compression_ratio),
+        ("cpu_steal_pct", stat.cpu_steal_pct),
+        ("cpu_softirq", stat.cpu_softirq),
+        ("cpu_iowait", stat.cpu_iowait),
+        ("cpu_nice", stat.cpu_nice),
+        ("cpu_power_watts", stat.cpu_power_watts),
+    ]
+}
+
+// -1.0 is `rated speed unknown` here, see status::get_net_utilization
+const UNKNOWN_AS_MINUS_ONE: [&str; 2] = ["rx_utilization_pct", "tx_utilization_pct"];
+
+fn is_negative(field: &str, value: f64) -> bool {
+    value.is_nan() || (value < 0.0 && !(value == -1.0 && UNKNOWN_AS_MINUS_ONE.contains(&field)))
+}
+
+fn signed_field_mut<'a>(stat: &'a mut StatRequest, field: &str) -> Option<&'a mut f64> {
+    match field {
+        "load_1" => Some(&mut stat.load_1),
+        "load_5" => Some(&mut stat.load_5),
+        "load_15" => Some(&mut stat.load_15),
+        "load_custom" => Some(&mut stat.load_custom),
+        "rx_utilization_pct" => Some(&mut stat.rx_utilization_pct),
+        "tx_utilization_pct" => Some(&mut stat.tx_utilization_pct),
+        "avg_rx_packet_size_bytes" => Some(&mut stat.avg_rx_packet_size_bytes),
+        "commit_ratio" => Some(&mut stat.commit_ratio),
+        "zram_compression_ratio" => Some(&mut stat.zram_compression_ratio),
+        "cpu_steal_pct" => Some(&mut stat.cpu_steal_pct),
+        "cpu_softirq" => Some(&mut stat.cpu_softirq),
+        "cpu_iowait" => Some(&mut stat.cpu_iowait),
+        "cpu_nice" => Some(&mut stat.cpu_nice),
+        "cpu_power_watts" => Some(&mut stat.cpu_power_watts),
+        _ => None,
+    }
+}
+
+pub fn validate(stat: &StatRequest) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    if !(0.0..=100.0).contains(&stat.cpu) {
+        errors.push(ValidationError::CpuOutOfRange(stat.cpu));
+    }
+    if stat.memory_used > stat.memory_total {
+        errors.push(ValidationError::MemoryUsedOverTotal {
+            used: stat.memory_used,
+            total: stat.memory_total,
+        });
+    }
+    if stat.swap_used > stat.swap_total {
+        errors.push(ValidationError::SwapUsedOverTotal {
+            used: stat.swap_used,
+            total: stat.swap_total,
+        });
+    }
+    if stat.hdd_used > stat.hdd_total {
+        errors.push(ValidationError::HddUsedOverTotal {
+            used: stat.hdd_used,
+            total: stat.hdd_total,
+        });
+    }
+    for (field, value) in signed_fields(stat) {
+        if is_negative(field, value) {
+            errors.push(ValidationError::Negative { field, value });
+        }
+    }
+    errors
+}
+
+// clamps every failed field to its nearest valid bound, NaN to 0
+pub fn clamp(stat: &mut StatRequest, errors: &[ValidationError]) {
+    for err in errors {
+        match err {
+            ValidationError::CpuOutOfRange(cpu) => {
+                stat.cpu = if cpu.is_nan() {
+                    0.0
+                } else {
+                    cpu.clamp(0.0, 100.0)
+                };
+            }
+            ValidationError::MemoryUsedOverTotal { total, .. } => stat.memory_used = *total,
+            ValidationError::SwapUsedOverTotal { total, .. } => stat.swap_used = *total,
+            ValidationError::HddUsedOverTotal { total, .. } => stat.hdd_used = *total,
+            ValidationError::Negative { field, .. } => {
+                if let Some(value) = signed_field_mut(stat, field) {
+                    *value = 0.0;
+                }
+            }
+        }
+    }
+}
+
+// warn! per failed check, then clamp
+pub fn sanitize(stat: &mut StatRequest) {
+    let errors = validate(stat);
+    for err in &errors {
+        warn!("implausible stat => {}", err);
+    }
+    clamp(stat, &errors);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plausible_stat() -> StatRequest {
+        StatRequest {
+            cpu: 12.0,
+            memory_total: 16_000,
+            memory_used: 4_000,
+            swap_total: 2_000,
+            swap_used: 100,
+            hdd_total: 100_000,
+            hdd_used: 40_000,
+            load_1: 0.5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn plausible_stat_passes() {
+        assert!(validate(&plausible_stat()).is_empty());
+        assert!(validate(&StatRequest::default()).is_empty());
+    }
+
+    #[test]
+    fn cpu_range() {
+        for (cpu, clamped) in [(99999.0, 100.0), (-3.0, 0.0), (f64::NAN, 0.0)] {
+            let mut stat = StatRequest {
+                cpu,
+                ..plausible_stat()
+            };
+            let errors = validate(&stat);
+            assert_eq!(errors.len(), 1);
+            assert!(matches!(errors[0], ValidationError::CpuOutOfRange(_)));
+            clamp(&mut stat, &errors);
+            assert_eq!(stat.cpu, clamped);
+        }
+        let stat = StatRequest {
+            cpu: 100.0,
+            ..plausible_stat()
+        };
+        assert!(validate(&stat).is_empty());
+    }
+
+    #[test]
+    fn memory_used_over_total() {
+        let mut stat = StatRequest {
+            memory_used: 20_000,
+            ..plausible_stat()
+        };
+        let errors = validate(&stat);
+        assert_eq!(
+            errors,
+            vec![ValidationError::MemoryUsedOverTotal {
+                used: 20_000,
+                total: 16_000
+            }]
+        );
+        clamp(&mut stat, &errors);
+        assert_eq!(stat.memory_used, 16_000);
+    }
+
+    #[test]
+    fn swap_used_over_total() {
+        let mut stat = StatRequest {
+            swap_total: 0,
+            ..plausible_stat()
+        };
+        let errors = validate(&stat);
+        assert_eq!(
+            errors,
+            vec![ValidationError::SwapUsedOverTotal {
+                used: 100,
+                total: 0
+            }]
+        );
+        clamp(&mut stat, &errors);
+        assert_eq!(stat.swap_used, 0);
+    }
+
+    #[test]
+    fn hdd_used_over_total() {
+        let mut stat = StatRequest {
+            hdd_used: u64::MAX,
+            ..plausible_stat()
+        };
+        let errors = validate(&stat);
+        assert_eq!(
+            errors,
+            vec![ValidationError::HddUsedOverTotal {
+                used: u64::MAX,
+                total: 100_000
+            }]
+        );
+        clamp(&mut stat, &errors);
+        assert_eq!(stat.hdd_used, 100_000);
+    }
+
+    #[test]
+    fn negative_rates() {
+        let mut stat = StatRequest {
+            load_1: -1.0,
+            rx_utilization_pct: f64::NAN,
+            ..plausible_stat()
+        };
+        let errors = validate(&stat);
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|err| matches!(
+            err,
+            ValidationError::Negative {
+                field: "load_1" | "rx_utilization_pct",
+                ..
+            }
+        )));
+        clamp(&mut stat, &errors);
+        assert_eq!(stat.load_1, 0.0);
+        assert_eq!(stat.rx_utilization_pct, 0.0);
+        assert!(validate(&stat).is_empty());
+    }
+
+    #[test]
+    fn unknown_utilization_passes() {
+        let mut stat = StatRequest {
+            rx_utilization_pct: -1.0,
+            tx_utilization_pct: -1.0,
+            ..plausible_stat()
+        };
+        sanitize(&mut stat);
+        assert_eq!(stat.rx_utilization_pct, -1.0);
+        assert_eq!(stat.tx_utilization_pct, -1.0);
+
+        stat.rx_utilization_pct = -2.0;
+        sanitize(&mut stat);
+        assert_eq!(stat.rx_utilization_pct, 0.0);
+    }
+}
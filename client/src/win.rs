@@ -0,0 +1,218 @@
+#![deny(warnings)]
+use lazy_static::lazy_static;
+use std::mem;
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+use windows_sys::Win32::Foundation::FILETIME;
+use windows_sys::Win32::NetworkManagement::IpHelper::{FreeMibTable, GetIfTable2, MIB_IF_TABLE2};
+use windows_sys::Win32::Storage::FileSystem::{
+    GetDiskFreeSpaceExW, GetDriveTypeW, GetLogicalDriveStringsW,
+};
+use windows_sys::Win32::System::SystemInformation::{
+    GetTickCount64, GlobalMemoryStatusEx, MEMORYSTATUSEX,
+};
+use windows_sys::Win32::System::Threading::GetSystemTimes;
+
+use crate::Args;
+use stat_client::procfs;
+use stat_client::procfs::IFACE_IGNORE_VEC;
+use stat_common::server_status::StatRequest;
+
+const SAMPLE_PERIOD: u64 = 1000; //ms
+const DRIVE_FIXED: u32 = 3;
+const IF_TYPE_SOFTWARE_LOOPBACK: u32 = 24;
+
+pub fn get_uptime() -> u64 {
+    unsafe { GetTickCount64() / 1000 }
+}
+
+// bytes
+pub fn get_memory() -> (u64, u64, u64, u64) {
+    let mut status: MEMORYSTATUSEX = unsafe { mem::zeroed() };
+    status.dwLength = mem::size_of::<MEMORYSTATUSEX>() as u32;
+    if unsafe { GlobalMemoryStatusEx(&mut status) } == 0 {
+        return (0, 0, 0, 0);
+    }
+
+    let mem_total = status.ullTotalPhys;
+    let mem_used = status.ullTotalPhys.saturating_sub(status.ullAvailPhys);
+    // page file = physical memory + swap
+    let swap_total = status.ullTotalPageFile.saturating_sub(status.ullTotalPhys);
+    let swap_free = status.ullAvailPageFile.saturating_sub(status.ullAvailPhys);
+
+    (mem_total, mem_used, swap_total, swap_free.min(swap_total))
+}
+
+// bytes, fixed drives only
+pub fn get_hdd() -> (u64, u64) {
+    let (mut hdd_total, mut hdd_used) = (0_u64, 0_u64);
+    let mut buf = [0_u16; 512];
+    let n = unsafe { GetLogicalDriveStringsW(buf.len() as u32, buf.as_mut_ptr()) } as usize;
+    for drive in buf[..n.min(buf.len())]
+        .split(|&c| c == 0)
+        .filter(|s| !s.is_empty())
+    {
+        let mut root = drive.to_vec();
+        root.push(0);
+        if unsafe { GetDriveTypeW(root.as_ptr()) } != DRIVE_FIXED {
+            continue;
+        }
+
+        let (mut avail, mut total, mut free) = (0_u64, 0_u64, 0_u64);
+        if unsafe { GetDiskFreeSpaceExW(root.as_ptr(), &mut avail, &mut total, &mut free) } != 0 {
+            hdd_total += total;
+            hdd_used += total.saturating_sub(free);
+        }
+    }
+
+    (hdd_total, hdd_used)
+}
+
+pub fn get_sys_traffic() -> (u64, u64) {
+    let (mut network_in, mut network_out) = (0_u64, 0_u64);
+    let mut table: *mut MIB_IF_TABLE2 = ptr::null_mut();
+    if unsafe { GetIfTable2(&mut table) } != 0 || table.is_null() {
+        return (0, 0);
+    }
+
+    let rows =
+        unsafe { slice::from_raw_parts((*table).Table.as_ptr(), (*table).NumEntries as usize) };
+    for row in rows {
+        // HardwareInterface: bit 0, FilterInterface: bit 1
+        let flags = row.InterfaceAndOperStatusFlags._bitfield;
+        if row.Type == IF_TYPE_SOFTWARE_LOOPBACK || flags & 0b01 == 0 || flags & 0b10 != 0 {
+            continue;
+        }
+        let alias = String::from_utf16_lossy(&row.Alias);
+        let name = alias.trim_end_matches('\0');
+        if IFACE_IGNORE_VEC.iter().any(|sk| name.contains(*sk)) {
+            continue;
+        }
+        network_in += row.InOctets;
+        network_out += row.OutOctets;
+    }
+    unsafe { FreeMibTable(table as _) };
+
+    (network_in, network_out)
+}
+
+#[derive(Debug, Default)]
+pub struct NetSpeed {
+    pub diff: f64,
+    pub clock: f64,
+    pub netrx: u64,
+    pub nettx: u64,
+    pub avgrx: u64,
+    pub avgtx: u64,
+}
+
+lazy_static! {
+    pub static ref G_NET_SPEED: Arc<Mutex<NetSpeed>> = Arc::new(Default::default());
+    pub static ref G_CPU_PERCENT: Arc<Mutex<f64>> = Arc::new(Default::default());
+}
+
+pub fn start_net_speed_collect_t() {
+    thread::spawn(|| loop {
+        let (avgrx, avgtx) = get_sys_traffic();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as f64
+            / 1000.0;
+
+        if let Ok(mut t) = G_NET_SPEED.lock() {
+            t.diff = now - t.clock;
+            t.clock = now;
+            t.netrx = procfs::calc_speed(avgrx, t.avgrx, t.diff);
+            t.nettx = procfs::calc_speed(avgtx, t.avgtx, t.diff);
+            t.avgrx = avgrx;
+            t.avgtx = avgtx;
+        }
+
+        thread::sleep(Duration::from_millis(SAMPLE_PERIOD));
+    });
+}
+
+fn filetime_u64(ft: &FILETIME) -> u64 {
+    (ft.dwHighDateTime as u64) << 32 | ft.dwLowDateTime as u64
+}
+
+// (idle, kernel + user), kernel time includes idle time
+fn get_system_times() -> Option<(u64, u64)> {
+    let (mut idle, mut kernel, mut user): (FILETIME, FILETIME, FILETIME) =
+        unsafe { (mem::zeroed(), mem::zeroed(), mem::zeroed()) };
+    if unsafe { GetSystemTimes(&mut idle, &mut kernel, &mut user) } == 0 {
+        return None;
+    }
+    Some((
+        filetime_u64(&idle),
+        filetime_u64(&kernel) + filetime_u64(&user),
+    ))
+}
+
+pub fn start_cpu_percent_collect_t() {
+    let mut pre = get_system_times().unwrap_or_default();
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(SAMPLE_PERIOD));
+
+        if let Some(cur) = get_system_times() {
+            let idle = procfs::wrapping_diff(cur.0, pre.0);
+            let total = procfs::wrapping_diff(cur.1, pre.1).max(1);
+            pre = cur;
+
+            if let Ok(mut cpu_percent) = G_CPU_PERCENT.lock() {
+                *cpu_percent = (100.0 - 100.0 * idle as f64 / total as f64)
+                    .max(0.0)
+                    .round();
+            }
+        }
+    });
+}
+
+pub fn sample(args: &Args, stat: &mut StatRequest) {
+    stat.version = env!("CARGO_PKG_VERSION").to_string();
+    // vnstat is linux only
+    stat.vnstat = false;
+
+    if args.collect_enabled("uptime") {
+        stat.uptime = get_uptime();
+    }
+
+    if args.collect_enabled("mem") {
+        let (mem_total, mem_used, swap_total, swap_free) = get_memory();
+        stat.memory_total = mem_total;
+        stat.memory_used = mem_used;
+        stat.swap_total = swap_total;
+        stat.swap_used = swap_total.saturating_sub(swap_free);
+    }
+
+    if args.collect_enabled("hdd") {
+        let (hdd_total, hdd_used) = get_hdd();
+        stat.hdd_total = hdd_total;
+        stat.hdd_used = hdd_used;
+    }
+
+    if args.collect_enabled("traffic") {
+        let (network_in, network_out) = get_sys_traffic();
+        stat.network_in = network_in;
+        stat.network_out = network_out;
+    }
+
+    if args.collect_enabled("cpu") {
+        if let Ok(o) = G_CPU_PERCENT.lock() {
+            stat.cpu = *o;
+        }
+    }
+
+    if args.collect_enabled("net") {
+        if let Ok(o) = G_NET_SPEED.lock() {
+            stat.network_rx = o.netrx;
+            stat.network_tx = o.nettx;
+        }
+    }
+}
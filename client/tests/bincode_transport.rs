@@ -0,0 +1,38 @@
+#![cfg(feature = "bincode-transport")]
+
+use prost::Message;
+
+use stat_common::bin_frame;
+
+mod common;
+use common::typical_stat;
+
+#[test]
+fn bincode_roundtrip() {
+    let stat = typical_stat();
+    let buf = bin_frame::encode(&stat).unwrap();
+    assert!(bin_frame::is_frame(&buf));
+    assert_eq!(bin_frame::decode(&buf).unwrap(), stat);
+}
+
+#[test]
+fn bincode_rejects_foreign_body() {
+    assert!(!bin_frame::is_frame(b"{\"name\":\"h1\"}"));
+    assert!(bin_frame::decode(b"SRST").is_err());
+    assert!(bin_frame::decode(b"SRST\x00\x09").is_err());
+}
+
+// size regression guard, run with --nocapture for the numbers
+#[test]
+fn bincode_payload_size() {
+    let stat = typical_stat();
+    let bin_len = bin_frame::encode(&stat).unwrap().len();
+    let json_len = serde_json::to_vec(&stat).unwrap().len();
+    let proto_len = stat.encoded_len();
+    println!(
+        "bincode {} bytes, json {} bytes, protobuf {} bytes",
+        bin_len, json_len, proto_len
+    );
+    assert!(bin_len < 512);
+    assert!(bin_len < json_len);
+}
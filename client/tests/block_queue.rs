@@ -0,0 +1,23 @@
+use stat_client::procfs::{get_block_inflight, get_block_scheduler};
+
+#[test]
+fn block_scheduler() {
+    assert_eq!(
+        get_block_scheduler("[mq-deadline] kyber bfq none\n").as_deref(),
+        Some("mq-deadline")
+    );
+    assert_eq!(
+        get_block_scheduler("mq-deadline kyber [bfq] none\n").as_deref(),
+        Some("bfq")
+    );
+    assert_eq!(get_block_scheduler("none\n").as_deref(), Some("none"));
+    assert_eq!(get_block_scheduler("mq-deadline none\n"), None);
+    assert_eq!(get_block_scheduler(""), None);
+}
+
+#[test]
+fn block_inflight() {
+    assert_eq!(get_block_inflight("       3        5\n"), Some(8));
+    assert_eq!(get_block_inflight("0 0\n"), Some(0));
+    assert_eq!(get_block_inflight("7\n"), None);
+}
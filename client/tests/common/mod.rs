@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use stat_common::server_status::StatRequest;
+
+// a typical full report, shared by the serialization tests
+pub fn typical_stat() -> StatRequest {
+    StatRequest {
+        name: "h1".to_string(),
+        version: "1.4.2".to_string(),
+        latest_ts: 1_660_000_000,
+        frame: "data".to_string(),
+        online4: true,
+        uptime: 3_600_000,
+        load_1: 0.42,
+        load_5: 0.35,
+        load_15: 0.3,
+        network_rx: 12_345,
+        network_tx: 6_789,
+        network_in: 987_654_321_000,
+        network_out: 123_456_789_000,
+        cpu: 12.5,
+        memory_total: 16_318_548,
+        memory_used: 4_318_548,
+        swap_total: 2_097_148,
+        hdd_total: 102_350,
+        hdd_used: 48_110,
+        cpu_model: "Intel(R) Xeon(R) CPU E5-2680 v4 @ 2.40GHz".to_string(),
+        cpu_cores_physical: 4,
+        cpu_cores_logical: 8,
+        timezone: "Europe/Berlin".to_string(),
+        utc_offset_sec: 7200,
+        collector_status: HashMap::from([
+            ("uptime".to_string(), true),
+            ("load".to_string(), true),
+            ("mem".to_string(), true),
+            ("hdd".to_string(), true),
+            ("traffic".to_string(), true),
+        ]),
+        disk_await_ms: HashMap::from([("sda".to_string(), 1.5), ("nvme0n1".to_string(), 0.2)]),
+        custom_metrics: HashMap::from([("queue_depth".to_string(), "17".to_string())]),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,71 @@
+use stat_client::procfs::{get_container_from_cgroup_reader, get_container_from_mountinfo_reader};
+
+const ID: &str = "3f1b9a7c2d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8";
+
+fn cgroup(path: &str) -> Option<(&'static str, String)> {
+    get_container_from_cgroup_reader(format!("12:memory:{}\n0::{}\n", path, path).as_bytes())
+}
+
+#[test]
+fn container_from_cgroup() {
+    let want = |runtime| Some((runtime, ID.to_string()));
+    assert_eq!(cgroup(&format!("/docker/{}", ID)), want("docker"));
+    assert_eq!(
+        cgroup(&format!("/system.slice/docker-{}.scope", ID)),
+        want("docker")
+    );
+    assert_eq!(
+        cgroup(&format!(
+            "/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod1a2b.slice/cri-containerd-{}.scope",
+            ID
+        )),
+        want("containerd")
+    );
+    assert_eq!(
+        cgroup(&format!("/kubepods/burstable/pod1a2b/{}", ID)),
+        want("kubernetes")
+    );
+    assert_eq!(
+        cgroup(&format!("/machine.slice/libpod-{}.scope/container", ID)),
+        want("podman")
+    );
+    assert_eq!(
+        cgroup(&format!("/kubepods.slice/crio-{}.scope", ID)),
+        want("cri-o")
+    );
+    // bare metal, or a private cgroup namespace
+    assert_eq!(cgroup("/user.slice/user-1000.slice/session-2.scope"), None);
+    assert_eq!(cgroup("/"), None);
+}
+
+#[test]
+fn container_from_mountinfo() {
+    let mountinfo = format!(
+        "\
+1046 1045 0:52 / / rw,relatime master:520 - overlay overlay rw,lowerdir=/var/lib/docker/overlay2/l/ABC,upperdir=/var/lib/docker/overlay2/{id}/diff
+1057 1046 254:1 /var/lib/docker/containers/{id}/resolv.conf /etc/resolv.conf rw,relatime - ext4 /dev/vda1 rw
+1058 1046 254:1 /var/lib/docker/containers/{id}/hostname /etc/hostname rw,relatime - ext4 /dev/vda1 rw
+",
+        id = ID
+    );
+    assert_eq!(
+        get_container_from_mountinfo_reader(mountinfo.as_bytes()),
+        Some(("docker", ID.to_string()))
+    );
+
+    let podman = format!(
+        "612 611 0:45 /containers/storage/overlay-containers/{}/userdata/hostname /etc/hostname rw - tmpfs tmpfs rw\n",
+        ID
+    );
+    assert_eq!(
+        get_container_from_mountinfo_reader(podman.as_bytes()),
+        Some(("podman", ID.to_string()))
+    );
+
+    // host, layer dirs only
+    let host = format!(
+        "30 1 254:1 /var/lib/docker/overlay2/{}/merged /mnt rw - ext4 /dev/vda1 rw\n",
+        ID
+    );
+    assert_eq!(get_container_from_mountinfo_reader(host.as_bytes()), None);
+}
@@ -0,0 +1,36 @@
+use stat_client::crypto::{decrypt, encrypt, parse_key, NONCE_LEN};
+
+const HEX_KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+#[test]
+fn round_trip() {
+    let key = parse_key(HEX_KEY).unwrap();
+    let plaintext = b"{\"name\":\"h1\",\"cpu\":1.0}";
+
+    let data = encrypt(&key, plaintext).unwrap();
+    assert_eq!(data.len(), NONCE_LEN + plaintext.len() + 16);
+    assert_eq!(decrypt(&key, &data).unwrap(), plaintext);
+
+    // fresh nonce per message
+    assert_ne!(encrypt(&key, plaintext).unwrap(), data);
+}
+
+#[test]
+fn tampered_or_wrong_key() {
+    let key = parse_key(HEX_KEY).unwrap();
+    let mut data = encrypt(&key, b"stat").unwrap();
+
+    assert!(decrypt(&[7; 32], &data).is_err());
+    let last = data.len() - 1;
+    data[last] ^= 1;
+    assert!(decrypt(&key, &data).is_err());
+    assert!(decrypt(&key, &data[..NONCE_LEN - 1]).is_err());
+}
+
+#[test]
+fn key_formats() {
+    let base64_key = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+    assert_eq!(parse_key(base64_key).unwrap(), parse_key(HEX_KEY).unwrap());
+    assert!(parse_key("0011").is_err());
+    assert!(parse_key("not a key").is_err());
+}
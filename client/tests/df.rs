@@ -0,0 +1,41 @@
+use stat_client::procfs::get_df_total_from_reader;
+
+#[test]
+fn df_total_row() {
+    let out = "\
+Filesystem     Type 1M-blocks  Used Available Use% Mounted on
+/dev/vda1      ext4     40188 12010     26119  32% /
+/dev/vdb1      xfs     102350  4811     97539   5% /data
+total          -       142538 16821    123658  12% -
+";
+    assert_eq!(
+        get_df_total_from_reader(out.as_bytes()),
+        Some((142538, 16821))
+    );
+}
+
+// df exits 1 on a stale nfs mount, stderr carries the error, stdout still has the total
+#[test]
+fn df_partial_failure() {
+    let out = "\
+Filesystem     Type 1M-blocks  Used Available Use% Mounted on
+/dev/vda1      ext4     40188 12010     26119  32% /
+total          -        40188 12010     26119  32% -
+
+";
+    assert_eq!(
+        get_df_total_from_reader(out.as_bytes()),
+        Some((40188, 12010))
+    );
+}
+
+#[test]
+fn df_no_total_row() {
+    assert_eq!(get_df_total_from_reader("".as_bytes()), None);
+    assert_eq!(
+        get_df_total_from_reader("df: no file systems processed\n".as_bytes()),
+        None
+    );
+    let header_only = "Filesystem     Type 1M-blocks  Used Available Use% Mounted on\n";
+    assert_eq!(get_df_total_from_reader(header_only.as_bytes()), None);
+}
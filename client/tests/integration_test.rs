@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use stat_common::server_status::StatRequest;
+
+// minimal http server, returns the first report body
+fn recv_report(listener: TcpListener) -> Vec<u8> {
+    recv_report_with_headers(listener).1
+}
+
+// header names lowercased
+fn recv_report_with_headers(listener: TcpListener) -> (HashMap<String, String>, Vec<u8>) {
+    let (stream, _) = listener.accept().unwrap();
+    let mut reader = BufReader::new(stream);
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((k, v)) = line.split_once(':') {
+            headers.insert(k.trim().to_lowercase(), v.trim().to_string());
+        }
+    }
+    let content_length = headers
+        .get("content-length")
+        .map_or(0, |v| v.parse::<usize>().unwrap());
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+
+    let resp = "{\"code\":0}";
+    let _ = write!(
+        reader.get_mut(),
+        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+        resp.len(),
+        resp
+    );
+
+    (headers, body)
+}
+
+#[test]
+fn report_to_mock_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(recv_report(listener));
+    });
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stat_client"))
+        .args(["-a", &format!("http://{}/report", addr)])
+        .args(["-u", "h1", "-p", "p1", "--json", "--disable-extra"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let body = rx.recv_timeout(Duration::from_secs(60));
+    let _ = child.kill();
+    let _ = child.wait();
+
+    let stat: StatRequest = serde_json::from_slice(&body.unwrap()).unwrap();
+    assert_eq!(stat.name, "h1");
+    assert_eq!(stat.version, env!("CARGO_PKG_VERSION"));
+    assert!(stat.cpu >= 0.0);
+    assert!(stat.load_1 >= 0.0);
+    assert!(stat.load_5 >= 0.0);
+    assert!(stat.load_15 >= 0.0);
+    assert!(stat.memory_total > 0);
+    assert!(stat.memory_used <= stat.memory_total);
+    assert!(stat.swap_used <= stat.swap_total);
+    assert!(stat.uptime > 0);
+}
+
+// --http-username goes to the proxy, the server still gets the node auth
+#[test]
+fn report_with_proxy_basic_auth() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(recv_report_with_headers(listener));
+    });
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stat_client"))
+        .args(["-a", &format!("http://{}/report", addr)])
+        .args(["-u", "h1", "-p", "p1", "--json", "--disable-extra"])
+        .args(["--http-username", "proxy", "--http-password", "pwd"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let report = rx.recv_timeout(Duration::from_secs(60));
+    let _ = child.kill();
+    let _ = child.wait();
+
+    let (headers, body) = report.unwrap();
+    // base64 of proxy:pwd and h1:p1
+    assert_eq!(headers["authorization"], "Basic cHJveHk6cHdk");
+    assert_eq!(headers[stat_common::NODE_AUTH_HEADER], "Basic aDE6cDE=");
+    let stat: StatRequest = serde_json::from_slice(&body).unwrap();
+    assert_eq!(stat.name, "h1");
+}
+
+// gVisor / FUSE style proc, files exist but reads fail or return garbage
+#[cfg(target_os = "linux")]
+#[test]
+fn report_with_broken_proc_root() {
+    use std::fs;
+
+    let root = std::env::temp_dir().join(format!("stat_proc_root_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("net/dev")).unwrap();
+    fs::create_dir_all(root.join("loadavg")).unwrap();
+    fs::write(root.join("uptime"), "garbage\n").unwrap();
+    fs::write(root.join("meminfo"), "").unwrap();
+    fs::write(root.join("stat"), "cpu x\n").unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(recv_report(listener));
+    });
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stat_client"))
+        .args(["-a", &format!("http://{}/report", addr)])
+        .args(["-u", "h1", "-p", "p1", "--json", "--disable-extra"])
+        .arg("--proc-root")
+        .arg(&root)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let body = rx.recv_timeout(Duration::from_secs(60));
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = fs::remove_dir_all(&root);
+
+    let stat: StatRequest = serde_json::from_slice(&body.unwrap()).unwrap();
+    assert_eq!(stat.name, "h1");
+    assert_eq!(stat.uptime, 0);
+    assert_eq!(stat.load_1, 0.0);
+    assert_eq!(stat.memory_total, 0);
+    assert_eq!(stat.network_in, 0);
+    assert_eq!(stat.cpu, 0.0);
+    assert_eq!(stat.collector_status.get("uptime"), Some(&false));
+    assert_eq!(stat.collector_status.get("load"), Some(&false));
+    assert_eq!(stat.collector_status.get("traffic"), Some(&false));
+}
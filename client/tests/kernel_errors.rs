@@ -0,0 +1,33 @@
+use stat_client::procfs::{get_kernel_errors_from_reader, KERNEL_ERROR_LINE_MAX};
+
+const DMESG: &str = "[    0.000000] Linux version 6.1.0-18-amd64
+[    1.204118] ACPI Error: AE_NOT_FOUND, While resolving a named reference package element
+[    2.511020] usb 1-1: new high-speed USB device number 2 using xhci_hcd
+[    5.100231] EXT4-fs (sda1): mounted filesystem with ordered data mode
+[  120.883412] blk_update_request: I/O error, dev sdb, sector 2048 op 0x0:(READ)
+[  121.000001] systemd[1]: Failed to start Load Kernel Modules.
+[  300.412000] BUG: kernel NULL pointer dereference, address: 0000000000000008
+[  301.000000] debugfs: Directory 'kvm' with parent '/' already present!
+";
+
+#[test]
+fn kernel_errors_last_n() {
+    let errors = get_kernel_errors_from_reader(DMESG.as_bytes(), 3);
+    assert_eq!(errors.len(), 3);
+    assert!(errors[0].contains("I/O error"));
+    assert!(errors[1].contains("Failed to start"));
+    assert!(errors[2].contains("BUG: kernel NULL pointer"));
+
+    assert_eq!(get_kernel_errors_from_reader(DMESG.as_bytes(), 10).len(), 4);
+    assert!(get_kernel_errors_from_reader(DMESG.as_bytes(), 0).is_empty());
+}
+
+#[test]
+fn kernel_errors_truncated() {
+    // multi byte chars straddle the cut
+    let line = format!("[    9.000000] error: {}\n", "é".repeat(200));
+    let errors = get_kernel_errors_from_reader(line.as_bytes(), 1);
+    assert!(errors[0].len() <= KERNEL_ERROR_LINE_MAX);
+    assert!(errors[0].len() > KERNEL_ERROR_LINE_MAX - 2);
+    assert!(line.starts_with(&errors[0]));
+}
@@ -0,0 +1,18 @@
+use stat_client::procfs::get_procs_running_from_reader;
+
+#[test]
+fn procs_running_and_blocked() {
+    let stat = "cpu  10 0 5 100 2 0 1 0 0 0\n\
+                ctxt 123456\n\
+                btime 1700000000\n\
+                processes 4242\n\
+                procs_running 3\n\
+                procs_blocked 1\n\
+                softirq 100 0 40 10 20 0 0 30 0 0 0\n";
+    assert_eq!(get_procs_running_from_reader(stat.as_bytes()), Some((3, 1)));
+    // both lines needed
+    assert_eq!(
+        get_procs_running_from_reader("procs_running 3\n".as_bytes()),
+        None
+    );
+}
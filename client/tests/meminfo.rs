@@ -0,0 +1,56 @@
+use std::io::Cursor;
+
+use stat_client::procfs::{
+    get_anon_huge_pages_from_reader, get_hugepages_from_reader, get_memory_commit_from_reader,
+    get_memory_from_reader,
+};
+
+// counts carry no unit, everything else is kB
+const MEMINFO: &str = "MemTotal:        8192000 kB
+MemFree:         4000000 kB
+Buffers:           50000 kB
+Cached:           100000 kB
+SwapCached:            0 kB
+SwapTotal:       2097148 kB
+SwapFree:        2000000 kB
+CommitLimit:     6193148 kB
+Committed_AS:    3000000 kB
+SReclaimable:      20000 kB
+AnonHugePages:     40960 kB
+HugePages_Total:     512
+HugePages_Free:      256
+HugePages_Rsvd:        0
+HugePages_Surp:        0
+Hugepagesize:       2048 kB
+";
+
+#[test]
+fn meminfo_unitless_counts() {
+    assert_eq!(get_hugepages_from_reader(Cursor::new(MEMINFO)), 512 * 2048);
+    // 1G pages, the count still unitless
+    assert_eq!(
+        get_hugepages_from_reader(Cursor::new(
+            "HugePages_Total:       4\nHugepagesize:    1048576 kB\n"
+        )),
+        4 * 1_048_576
+    );
+}
+
+#[test]
+fn meminfo_kb_fields_unchanged() {
+    let (total, used, swap_total, swap_free) = get_memory_from_reader(Cursor::new(MEMINFO));
+    assert_eq!(total, 8_192_000);
+    assert_eq!(
+        used,
+        8_192_000 - 4_000_000 - 50_000 - 100_000 - 20_000 - 512 * 2048
+    );
+    assert_eq!((swap_total, swap_free), (2_097_148, 2_000_000));
+    assert_eq!(
+        get_memory_commit_from_reader(Cursor::new(MEMINFO)),
+        (3_000_000, 6_193_148)
+    );
+    assert_eq!(
+        get_anon_huge_pages_from_reader(Cursor::new(MEMINFO)),
+        40_960
+    );
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "msgpack")]
+
+use std::time::Instant;
+
+mod common;
+use common::typical_stat;
+
+// the server decodes into a serde_json::Value, same as the json body
+#[test]
+fn msgpack_decodes_like_json() {
+    let stat = typical_stat();
+    let buf = rmp_serde::to_vec_named(&stat).unwrap();
+    let value: serde_json::Value = rmp_serde::from_slice(&buf).unwrap();
+    assert_eq!(value, serde_json::to_value(&stat).unwrap());
+    assert!(buf.len() < serde_json::to_vec(&stat).unwrap().len());
+}
+
+// cargo test --features msgpack --test msgpack -- --ignored --nocapture
+#[test]
+#[ignore]
+fn bench_json_vs_msgpack() {
+    let stat = typical_stat();
+    let rounds = 100_000;
+
+    let start = Instant::now();
+    let mut json = Vec::new();
+    for _ in 0..rounds {
+        json = serde_json::to_vec(&stat).unwrap();
+    }
+    let json_encode = start.elapsed();
+    let start = Instant::now();
+    for _ in 0..rounds {
+        let _: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    }
+    let json_decode = start.elapsed();
+
+    let start = Instant::now();
+    let mut msgpack = Vec::new();
+    for _ in 0..rounds {
+        msgpack = rmp_serde::to_vec_named(&stat).unwrap();
+    }
+    let msgpack_encode = start.elapsed();
+    let start = Instant::now();
+    for _ in 0..rounds {
+        let _: serde_json::Value = rmp_serde::from_slice(&msgpack).unwrap();
+    }
+    let msgpack_decode = start.elapsed();
+
+    println!(
+        "json    {} bytes, encode {:?}, decode {:?}",
+        json.len(),
+        json_encode / rounds,
+        json_decode / rounds
+    );
+    println!(
+        "msgpack {} bytes, encode {:?}, decode {:?}",
+        msgpack.len(),
+        msgpack_encode / rounds,
+        msgpack_decode / rounds
+    );
+}
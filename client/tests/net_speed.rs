@@ -0,0 +1,28 @@
+use proptest::prelude::*;
+
+use stat_client::procfs::{calc_speed, wrapping_diff};
+
+proptest! {
+    #[test]
+    fn speed_is_bounded(pre in any::<u64>(), cur in any::<u64>(), diff in 0.001_f64..3600.0) {
+        let speed = calc_speed(cur, pre, diff);
+        prop_assert!(speed as f64 <= u64::MAX as f64 / diff);
+    }
+
+    #[test]
+    fn speed_of_monotonic_counter(pre in any::<u32>(), delta in any::<u32>(), diff in 1_u64..3600) {
+        let (pre, cur) = (pre as u64, pre as u64 + delta as u64);
+        prop_assert_eq!(calc_speed(cur, pre, diff as f64), delta as u64 / diff);
+    }
+
+    #[test]
+    fn rollover_wraps(pre in any::<u64>(), delta in any::<u32>()) {
+        let cur = pre.wrapping_add(delta as u64);
+        prop_assert_eq!(wrapping_diff(cur, pre), delta as u64);
+    }
+
+    #[test]
+    fn zero_diff_is_zero(pre in any::<u64>(), cur in any::<u64>()) {
+        prop_assert_eq!(calc_speed(cur, pre, 0.0), 0);
+    }
+}
@@ -0,0 +1,36 @@
+use std::env;
+use std::fs;
+
+use stat_client::output::{FileOutput, NullOutput, Output};
+use stat_common::server_status::StatRequest;
+
+#[test]
+fn file_output_appends_json_lines() {
+    let path = env::temp_dir().join(format!("stat_output_{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    for name in ["h1", "h2"] {
+        // reopened, the second run appends
+        let mut out = FileOutput::open(&path).unwrap();
+        let stat = StatRequest {
+            name: name.to_string(),
+            cpu: 12.5,
+            ..Default::default()
+        };
+        out.write(&stat).unwrap();
+    }
+
+    let contents = fs::read_to_string(&path).unwrap();
+    let names = contents
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["name"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["h1", "h2"]);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn null_output() {
+    assert!(NullOutput.write(&StatRequest::default()).is_ok());
+}
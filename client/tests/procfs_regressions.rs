@@ -0,0 +1,88 @@
+// inputs that panicked the /proc parsers before they were made panic free, see client/fuzz
+use stat_client::procfs::{
+    get_loadavg_from_reader, get_memory_from_reader, get_sys_traffic_from_reader,
+};
+
+const NET_DEV_HEADER: &str = "Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+";
+
+#[test]
+fn loadavg_empty() {
+    assert_eq!(get_loadavg_from_reader(&b""[..]), (0.0, 0.0, 0.0));
+}
+
+#[test]
+fn loadavg_short_line() {
+    // indexed a[2] of two parsed values
+    assert_eq!(
+        get_loadavg_from_reader(&b"0.52 0.58\n"[..]),
+        (0.0, 0.0, 0.0)
+    );
+}
+
+#[test]
+fn loadavg_not_numeric() {
+    assert_eq!(
+        get_loadavg_from_reader(&b"0.52 x 0.59 1/467 12345\n"[..]),
+        (0.0, 0.0, 0.0)
+    );
+}
+
+#[test]
+fn loadavg_invalid_utf8() {
+    assert_eq!(
+        get_loadavg_from_reader(&b"\xff\xfe 0.1 0.2\n"[..]),
+        (0.0, 0.0, 0.0)
+    );
+}
+
+#[test]
+fn meminfo_value_missing() {
+    // `\d*` matched nothing and the empty value was unwrapped
+    assert_eq!(
+        get_memory_from_reader(&b"MemTotal:        kB\nMemFree: x kB\n"[..]),
+        (0, 0, 0, 0)
+    );
+}
+
+#[test]
+fn meminfo_free_over_total() {
+    // MemTotal - MemFree underflowed
+    let (total, used, _, _) =
+        get_memory_from_reader(&b"MemTotal: 1000 kB\nMemFree: 4000 kB\nCached: 9000 kB\n"[..]);
+    assert_eq!((total, used), (1000, 0));
+}
+
+#[test]
+fn meminfo_value_over_u64() {
+    let (total, _, _, _) = get_memory_from_reader(&b"MemTotal: 99999999999999999999999 kB\n"[..]);
+    assert_eq!(total, 0);
+}
+
+#[test]
+fn net_dev_short_line() {
+    let input = format!("{}  eth0: 1024 8 0 0\n", NET_DEV_HEADER);
+    assert_eq!(get_sys_traffic_from_reader(input.as_bytes()), (0, 0));
+}
+
+#[test]
+fn net_dev_counter_over_u64() {
+    let input = format!(
+        "{}  eth0: 99999999999999999999999 1 0 0 0 0 0 0 2048 1 0 0 0 0 0 0\n",
+        NET_DEV_HEADER
+    );
+    assert_eq!(get_sys_traffic_from_reader(input.as_bytes()), (0, 0));
+}
+
+#[test]
+fn net_dev_sum_overflow() {
+    // the per interface sum overflowed before it saturated
+    let max = u64::MAX;
+    let input = format!(
+        "{h}  eth0: {m} 1 0 0 0 0 0 0 {m} 1 0 0 0 0 0 0\n  eth1: {m} 1 0 0 0 0 0 0 {m} 1 0 0 0 0 0 0\n",
+        h = NET_DEV_HEADER,
+        m = max
+    );
+    assert_eq!(get_sys_traffic_from_reader(input.as_bytes()), (max, max));
+}
@@ -0,0 +1,43 @@
+use std::net::IpAddr;
+
+use stat_client::procfs::{decode_proc_net_addr, get_tcp_remote_addrs_from_reader};
+
+// little endian hosts, where the kernel prints each word byte swapped
+#[cfg(target_endian = "little")]
+#[test]
+fn proc_net_addr() {
+    assert_eq!(
+        decode_proc_net_addr("0100007F"),
+        Some("127.0.0.1".parse::<IpAddr>().unwrap())
+    );
+    assert_eq!(
+        decode_proc_net_addr("B80D01200000000000000000020000FE"),
+        Some("2001:db8::fe00:2".parse::<IpAddr>().unwrap())
+    );
+    // ::ffff:203.0.113.7
+    assert_eq!(
+        decode_proc_net_addr("0000000000000000FFFF0000077100CB"),
+        Some("203.0.113.7".parse::<IpAddr>().unwrap())
+    );
+    assert_eq!(decode_proc_net_addr("7F"), None);
+}
+
+#[cfg(target_endian = "little")]
+#[test]
+fn tcp_remote_addrs() {
+    let tcp = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1 1 0000000000000000 100 0 0 10 0
+   1: 0A00000A:0016 077100CB:D431 01 00000000:00000000 02:000A7B12 00000000     0        0 2 4 0000000000000000 20 4 31 10 -1
+   2: 0A00000A:0050 077100CB:D432 01 00000000:00000000 02:000A7B12 00000000     0        0 3 4 0000000000000000 20 4 31 10 -1
+   3: 0100007F:1F90 0100007F:A3C2 01 00000000:00000000 00:00000000 00000000     0        0 4 1 0000000000000000 20 4 30 10 -1
+   4: 0A00000A:0050 087100CB:D433 06 00000000:00000000 03:00000D3C 00000000     0        0 0 3 0000000000000000
+";
+    assert_eq!(
+        get_tcp_remote_addrs_from_reader(tcp.as_bytes()),
+        vec![
+            "203.0.113.7".parse::<IpAddr>().unwrap(),
+            "203.0.113.7".parse::<IpAddr>().unwrap(),
+        ]
+    );
+}
@@ -0,0 +1,45 @@
+use stat_client::procfs::{get_iptables_counter_bytes_from_reader, get_nft_counter_bytes};
+
+#[test]
+fn nft_rule_counter() {
+    let json = r#"{"nftables": [
+        {"metainfo": {"version": "1.0.2", "json_schema_version": 1}},
+        {"table": {"family": "inet", "name": "acct", "handle": 1}},
+        {"rule": {"family": "inet", "table": "acct", "chain": "input", "handle": 3,
+            "comment": "billing_in",
+            "expr": [{"match": {"op": "==", "left": {"meta": {"key": "iifname"}}, "right": "eth0"}},
+                     {"counter": {"packets": 120, "bytes": 98304}}]}},
+        {"rule": {"family": "inet", "table": "acct", "chain": "output", "handle": 4,
+            "comment": "billing_out",
+            "expr": [{"counter": {"packets": 80, "bytes": 4096}}]}}
+    ]}"#;
+    assert_eq!(
+        get_nft_counter_bytes(json, "acct", "input", "billing_in"),
+        Some(98304)
+    );
+    assert_eq!(
+        get_nft_counter_bytes(json, "acct", "output", "billing_out"),
+        Some(4096)
+    );
+    assert_eq!(
+        get_nft_counter_bytes(json, "acct", "input", "billing_out"),
+        None
+    );
+}
+
+#[test]
+fn iptables_rule_counter() {
+    let out = "\
+Chain INPUT (policy ACCEPT 0 packets, 0 bytes)
+    pkts      bytes target     prot opt in     out     source               destination
+     120    98304            all  --  eth0   *       0.0.0.0/0            0.0.0.0/0            /* billing_in */
+";
+    assert_eq!(
+        get_iptables_counter_bytes_from_reader(out.as_bytes(), "billing_in"),
+        Some(98304)
+    );
+    assert_eq!(
+        get_iptables_counter_bytes_from_reader(out.as_bytes(), "billing_out"),
+        None
+    );
+}
@@ -0,0 +1,27 @@
+use stat_client::units::{to_legacy_units, KIB, MIB};
+use stat_common::server_status::StatRequest;
+
+#[test]
+fn legacy_units_from_bytes() {
+    let mut stat = StatRequest {
+        memory_total: 2_048_000 * KIB,
+        memory_used: 1_376_000 * KIB,
+        swap_total: 1_024_000 * KIB,
+        swap_used: 24_000 * KIB,
+        effective_memory_limit: 2_048_000 * KIB,
+        hdd_total: 102_350 * MIB,
+        // partial MiB rounds down, same as df -m
+        hdd_used: 48_110 * MIB + 512 * KIB,
+        byte_units: true,
+        ..Default::default()
+    };
+    to_legacy_units(&mut stat);
+    assert_eq!(
+        (stat.memory_total, stat.memory_used),
+        (2_048_000, 1_376_000)
+    );
+    assert_eq!((stat.swap_total, stat.swap_used), (1_024_000, 24_000));
+    assert_eq!(stat.effective_memory_limit, 2_048_000);
+    assert_eq!((stat.hdd_total, stat.hdd_used), (102_350, 48_110));
+    assert!(!stat.byte_units);
+}
@@ -0,0 +1,38 @@
+// compact StatRequest transport, replaces json on constrained links
+//
+// frame: `SRST` magic (4 bytes) || version (u16 big endian) || bincode StatRequest
+// bincode runs with varint ints, most counters and all zero fields take a single byte
+use bincode::Options;
+
+use crate::server_status::StatRequest;
+
+pub const MAGIC: &[u8; 4] = b"SRST";
+pub const VERSION: u16 = 1;
+pub const HEADER_LEN: usize = 6;
+
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+pub fn is_frame(buf: &[u8]) -> bool {
+    buf.starts_with(MAGIC)
+}
+
+pub fn encode(stat: &StatRequest) -> anyhow::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(512);
+    buf.extend_from_slice(MAGIC);
+    buf.extend_from_slice(&VERSION.to_be_bytes());
+    options().serialize_into(&mut buf, stat)?;
+    Ok(buf)
+}
+
+pub fn decode(buf: &[u8]) -> anyhow::Result<StatRequest> {
+    if buf.len() < HEADER_LEN || !is_frame(buf) {
+        anyhow::bail!("not a bincode frame");
+    }
+    let version = u16::from_be_bytes([buf[4], buf[5]]);
+    if version != VERSION {
+        anyhow::bail!("unsupported bincode frame version {}", version);
+    }
+    Ok(options().deserialize(&buf[HEADER_LEN..])?)
+}
@@ -1,3 +1,12 @@
+// generated by prost
+#[allow(clippy::all)]
 pub mod server_status {
     tonic::include_proto!("server_status");
 }
+
+#[cfg(feature = "bincode-transport")]
+pub mod bin_frame;
+pub mod packed;
+
+// node -u/-p when Authorization is taken by a basic auth reverse proxy
+pub const NODE_AUTH_HEADER: &str = "x-node-authorization";
@@ -0,0 +1,252 @@
+// fixed layout StatRequest transport, only the core fields, for huge fleets
+//
+// all integers big endian, v1 frame:
+//
+//   offset  size  field
+//   0       4     `SRSP` magic
+//   4       1     version, 1
+//   5       1     flags, bit 0 online4, bit 1 online6, bit 2 vnstat, bit 3 byte_units
+//   6       1     name length n, at most 255
+//   7       n     name, utf8
+//   7+n     1     version length m, at most 255
+//   8+n     m     version, utf8
+//   8+n+m   112   u64 x 14: latest_ts, uptime, network_rx, network_tx, network_in,
+//                 network_out, last_network_in, last_network_out, memory_total,
+//                 memory_used, swap_total, swap_used, hdd_total, hdd_used
+//   120+n+m 16    f32 x 4: load_1, load_5, load_15, cpu
+//
+// units follow the protobuf field docs, the f32 loads and cpu are lossy past 7 digits,
+// every other field decodes to its default and frame to `data`
+use crate::server_status::StatRequest;
+
+pub const MAGIC: &[u8; 4] = b"SRSP";
+pub const VERSION: u8 = 1;
+// magic, version, flags
+pub const HEADER_LEN: usize = 6;
+// u64 x 14 and f32 x 4
+pub const FIXED_LEN: usize = 14 * 8 + 4 * 4;
+
+const FLAG_ONLINE4: u8 = 1;
+const FLAG_ONLINE6: u8 = 1 << 1;
+const FLAG_VNSTAT: u8 = 1 << 2;
+const FLAG_BYTE_UNITS: u8 = 1 << 3;
+
+pub fn is_frame(buf: &[u8]) -> bool {
+    buf.starts_with(MAGIC)
+}
+
+fn put_str(buf: &mut Vec<u8>, field: &str, s: &str) -> anyhow::Result<()> {
+    if s.len() > u8::MAX as usize {
+        anyhow::bail!("{} longer than {} bytes", field, u8::MAX);
+    }
+    buf.push(s.len() as u8);
+    buf.extend_from_slice(s.as_bytes());
+    Ok(())
+}
+
+pub fn encode(stat: &StatRequest) -> anyhow::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(HEADER_LEN + 2 + FIXED_LEN + 32);
+    buf.extend_from_slice(MAGIC);
+    buf.push(VERSION);
+    let mut flags = 0;
+    for (set, flag) in [
+        (stat.online4, FLAG_ONLINE4),
+        (stat.online6, FLAG_ONLINE6),
+        (stat.vnstat, FLAG_VNSTAT),
+        (stat.byte_units, FLAG_BYTE_UNITS),
+    ] {
+        if set {
+            flags |= flag;
+        }
+    }
+    buf.push(flags);
+    put_str(&mut buf, "name", &stat.name)?;
+    put_str(&mut buf, "version", &stat.version)?;
+    for v in [
+        stat.latest_ts,
+        stat.uptime,
+        stat.network_rx,
+        stat.network_tx,
+        stat.network_in,
+        stat.network_out,
+        stat.last_network_in,
+        stat.last_network_out,
+        stat.memory_total,
+        stat.memory_used,
+        stat.swap_total,
+        stat.swap_used,
+        stat.hdd_total,
+        stat.hdd_used,
+    ] {
+        buf.extend_from_slice(&v.to_be_bytes());
+    }
+    for v in [stat.load_1, stat.load_5, stat.load_15, stat.cpu] {
+        buf.extend_from_slice(&(v as f32).to_be_bytes());
+    }
+    Ok(buf)
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        if self.buf.len() - self.pos < n {
+            anyhow::bail!("packed frame truncated at {}", self.pos);
+        }
+        let b = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(b)
+    }
+
+    fn str(&mut self) -> anyhow::Result<String> {
+        let n = self.take(1)?[0] as usize;
+        Ok(std::str::from_utf8(self.take(n)?)?.to_string())
+    }
+
+    fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn f32(&mut self) -> anyhow::Result<f64> {
+        Ok(f32::from_be_bytes(self.take(4)?.try_into()?) as f64)
+    }
+}
+
+pub fn decode(buf: &[u8]) -> anyhow::Result<StatRequest> {
+    if buf.len() < HEADER_LEN || !is_frame(buf) {
+        anyhow::bail!("not a packed frame");
+    }
+    if buf[4] != VERSION {
+        anyhow::bail!("unsupported packed frame version {}", buf[4]);
+    }
+    let flags = buf[5];
+    let mut r = Reader {
+        buf,
+        pos: HEADER_LEN,
+    };
+    let mut stat = StatRequest {
+        frame: "data".to_string(),
+        online4: flags & FLAG_ONLINE4 != 0,
+        online6: flags & FLAG_ONLINE6 != 0,
+        vnstat: flags & FLAG_VNSTAT != 0,
+        byte_units: flags & FLAG_BYTE_UNITS != 0,
+        name: r.str()?,
+        version: r.str()?,
+        ..Default::default()
+    };
+    for v in [
+        &mut stat.latest_ts,
+        &mut stat.uptime,
+        &mut stat.network_rx,
+        &mut stat.network_tx,
+        &mut stat.network_in,
+        &mut stat.network_out,
+        &mut stat.last_network_in,
+        &mut stat.last_network_out,
+        &mut stat.memory_total,
+        &mut stat.memory_used,
+        &mut stat.swap_total,
+        &mut stat.swap_used,
+        &mut stat.hdd_total,
+        &mut stat.hdd_used,
+    ] {
+        *v = r.u64()?;
+    }
+    for v in [
+        &mut stat.load_1,
+        &mut stat.load_5,
+        &mut stat.load_15,
+        &mut stat.cpu,
+    ] {
+        *v = r.f32()?;
+    }
+    if r.pos != buf.len() {
+        anyhow::bail!("{} trailing bytes in packed frame", buf.len() - r.pos);
+    }
+    Ok(stat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // only the core fields, floats exact in f32
+    fn core_stat() -> StatRequest {
+        StatRequest {
+            name: "h1".to_string(),
+            version: "1.4.2".to_string(),
+            latest_ts: 1_660_000_000,
+            frame: "data".to_string(),
+            online4: true,
+            byte_units: true,
+            uptime: 3_600_000,
+            load_1: 0.5,
+            load_5: 0.375,
+            load_15: 0.25,
+            network_rx: 12_345,
+            network_tx: 6_789,
+            network_in: 987_654_321_000,
+            network_out: 123_456_789_000,
+            last_network_in: 987_000_000_000,
+            last_network_out: 123_000_000_000,
+            cpu: 12.5,
+            memory_total: 16_318_548 * 1024,
+            memory_used: 4_318_548 * 1024,
+            swap_total: 2_097_148 * 1024,
+            swap_used: 0,
+            hdd_total: 102_350 << 20,
+            hdd_used: 48_110 << 20,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn packed_roundtrip() {
+        let stat = core_stat();
+        let buf = encode(&stat).unwrap();
+        assert!(is_frame(&buf));
+        assert_eq!(
+            buf.len(),
+            HEADER_LEN + 1 + stat.name.len() + 1 + stat.version.len() + FIXED_LEN
+        );
+        assert_eq!(decode(&buf).unwrap(), stat);
+        assert!(buf.len() < serde_json::to_vec(&stat).unwrap().len() / 3);
+    }
+
+    #[test]
+    fn packed_drops_non_core_fields() {
+        let stat = StatRequest {
+            cpu_model: "Intel(R) Xeon(R) CPU E5-2680 v4 @ 2.40GHz".to_string(),
+            disk_await_ms: HashMap::from([("sda".to_string(), 1.5)]),
+            load_1: 0.1,
+            ..core_stat()
+        };
+        let decoded = decode(&encode(&stat).unwrap()).unwrap();
+        assert!(decoded.cpu_model.is_empty());
+        assert!(decoded.disk_await_ms.is_empty());
+        assert!((decoded.load_1 - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn packed_rejects_bad_frames() {
+        let buf = encode(&core_stat()).unwrap();
+        assert!(decode(&buf[..buf.len() - 1]).is_err());
+        let mut trailing = buf.clone();
+        trailing.push(0);
+        assert!(decode(&trailing).is_err());
+        let mut version = buf;
+        version[4] = 2;
+        assert!(decode(&version).is_err());
+        assert!(decode(b"{\"name\":\"h1\"}").is_err());
+
+        let long_name = StatRequest {
+            name: "h".repeat(256),
+            ..core_stat()
+        };
+        assert!(encode(&long_name).is_err());
+    }
+}
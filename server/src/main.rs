@@ -5,13 +5,14 @@ extern crate log;
 extern crate pretty_env_logger;
 #[macro_use]
 extern crate prettytable;
-use bytes::Buf;
 use clap::Parser;
 use http_auth_basic::Credentials;
 use minijinja::context;
 use once_cell::sync::OnceCell;
 use prost::Message;
 use rust_embed::RustEmbed;
+use stat_common::bin_frame;
+use stat_common::packed;
 use stat_common::server_status::StatRequest;
 use std::collections::HashMap;
 use std::process;
@@ -36,6 +37,7 @@ type Result<T> = std::result::Result<T, GenericError>;
 static NOTFOUND: &[u8] = b"Not Found";
 static UNAUTHORIZED: &[u8] = b"Unauthorized";
 static INTERNAL_SERVER_ERROR: &[u8] = b"Internal Server Error";
+static MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
 
 static G_CONFIG: OnceCell<crate::config::Config> = OnceCell::new();
 static G_STATS_MGR: OnceCell<crate::stats::StatsMgr> = OnceCell::new();
@@ -58,17 +60,22 @@ struct Args {
     cloud: bool,
 }
 
+// node auth, the node header wins when a basic auth proxy in front owns Authorization
+fn report_credentials(headers: &hyper::HeaderMap) -> Option<Credentials> {
+    let auth = headers
+        .get(stat_common::NODE_AUTH_HEADER)
+        .or_else(|| headers.get(header::AUTHORIZATION))?;
+    Credentials::from_header(auth.to_str().ok()?.to_string()).ok()
+}
+
 // stat report
 async fn stats_report(req: Request<Body>) -> Result<Response<Body>> {
     let req_header = req.headers();
     // auth
     let mut auth_ok = false;
-    if let Some(auth) = req_header.get(hyper::header::AUTHORIZATION) {
-        let auth_header_value = auth.to_str()?.to_string();
-        if let Ok(credentials) = Credentials::from_header(auth_header_value) {
-            if let Some(cfg) = G_CONFIG.get() {
-                auth_ok = cfg.auth(&credentials.user_id, &credentials.password);
-            }
+    if let Some(credentials) = report_credentials(req_header) {
+        if let Some(cfg) = G_CONFIG.get() {
+            auth_ok = cfg.auth(&credentials.user_id, &credentials.password);
         }
     }
     if !auth_ok {
@@ -85,11 +92,22 @@ async fn stats_report(req: Request<Body>) -> Result<Response<Body>> {
         .clone()
         .to_str()
     {
-        let whole_body = hyper::body::aggregate(req).await?;
+        let whole_body = hyper::body::to_bytes(req).await?;
         // dbg!(content_type);
-        if content_type.eq(&mime::APPLICATION_JSON.to_string()) {
+        if bin_frame::is_frame(&whole_body) {
+            // bincode, detected by the magic whatever the content type says
+            let stat = bin_frame::decode(&whole_body)?;
+            json_data = Some(serde_json::to_value(stat)?);
+        } else if packed::is_frame(&whole_body) {
+            // --wire-format binary, core fields only
+            let stat = packed::decode(&whole_body)?;
+            json_data = Some(serde_json::to_value(stat)?);
+        } else if content_type.eq(&mime::APPLICATION_JSON.to_string()) {
             // json
-            json_data = Some(serde_json::from_reader(whole_body.reader())?);
+            json_data = Some(serde_json::from_slice(&whole_body)?);
+        } else if content_type.eq(MSGPACK_CONTENT_TYPE) {
+            // msgpack, named maps decode schemaless like json
+            json_data = Some(rmp_serde::from_slice(&whole_body)?);
         } else if content_type.eq(&mime::APPLICATION_OCTET_STREAM.to_string()) {
             // protobuf
             let stat = StatRequest::decode(whole_body)?;
@@ -434,3 +452,28 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_credentials_behind_proxy_auth() {
+        let mut headers = hyper::HeaderMap::new();
+        // proxy:pwd
+        headers.insert(header::AUTHORIZATION, "Basic cHJveHk6cHdk".parse().unwrap());
+        let credentials = report_credentials(&headers).unwrap();
+        assert_eq!(credentials.user_id, "proxy");
+
+        // h1:p1, what the client sends with --http-username
+        headers.insert(
+            stat_common::NODE_AUTH_HEADER,
+            "Basic aDE6cDE=".parse().unwrap(),
+        );
+        let credentials = report_credentials(&headers).unwrap();
+        assert_eq!(
+            (credentials.user_id.as_str(), credentials.password.as_str()),
+            ("h1", "p1")
+        );
+    }
+}
@@ -1,6 +1,7 @@
 #![deny(warnings)]
 use serde::{Deserialize, Serialize};
-use stat_common::server_status::{IpInfo, SysInfo};
+use stat_common::server_status::{IpInfo, NumaMemory, SysInfo};
+use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 fn default_as_true() -> bool {
@@ -30,6 +31,9 @@ pub struct HostStat {
     pub uptime: u64,
     #[serde(rename(serialize = "uptime"), skip_deserializing)]
     pub uptime_str: String,
+    // unix secs, 0 from clients that predate it
+    #[serde(default)]
+    pub boot_time: u64,
 
     pub load_1: f64,
     pub load_5: f64,
@@ -50,11 +54,37 @@ pub struct HostStat {
     pub memory_used: u64,
     pub swap_total: u64,
     pub swap_used: u64,
+    #[serde(default)]
+    pub effective_memory_limit: u64,
     pub hdd_total: u64,
     pub hdd_used: u64,
+    #[serde(default)]
+    pub readonly_mounts: Vec<String>,
+    #[serde(default)]
+    pub numa_memory: Vec<NumaMemory>,
+    #[serde(default)]
+    pub reboot_required: bool,
+    #[serde(default)]
+    pub maintenance: bool,
+    #[serde(default)]
+    pub container_runtime: String,
+    #[serde(default)]
+    pub container_id: String,
 
     #[serde(skip_deserializing)]
     pub custom: String,
+    #[serde(default)]
+    pub custom_metrics: HashMap<String, String>,
+    #[serde(default)]
+    pub collector_status: HashMap<String, bool>,
+    // "heartbeat" for liveness only reports, the last full report is kept
+    #[serde(default, skip_serializing)]
+    pub frame: String,
+    #[serde(default)]
+    pub seq: u64,
+    // memory/swap/hdd arrived in bytes, see normalize_units
+    #[serde(default, skip_serializing)]
+    pub byte_units: bool,
 
     #[serde(skip_serializing)]
     pub ip_info: Option<IpInfo>,
@@ -71,6 +101,27 @@ pub struct HostStat {
     pub disabled: bool,
 }
 
+impl HostStat {
+    // the web ui reads memory/swap in KiB and hdd in MiB
+    pub fn normalize_units(&mut self) {
+        if !self.byte_units {
+            return;
+        }
+        for v in [
+            &mut self.memory_total,
+            &mut self.memory_used,
+            &mut self.swap_total,
+            &mut self.swap_used,
+            &mut self.effective_memory_limit,
+        ] {
+            *v /= 1024;
+        }
+        self.hdd_total /= 1024 * 1024;
+        self.hdd_used /= 1024 * 1024;
+        self.byte_units = false;
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StatsResp {
     pub updated: u64,
@@ -30,6 +30,20 @@ pub struct StatsMgr {
     stats_data: Arc<Mutex<StatsResp>>,
 }
 
+fn uptime_str(uptime: u64) -> String {
+    let day = (uptime as f64 / 3600.0 / 24.0) as i64;
+    if day > 0 {
+        format!("{} 天", day)
+    } else {
+        format!(
+            "{:02}:{:02}:{:02}",
+            (uptime as f64 / 3600.0) as i64,
+            (uptime as f64 / 60.0) as i64 % 60,
+            uptime % 60
+        )
+    }
+}
+
 impl StatsMgr {
     pub fn new() -> Self {
         Self {
@@ -95,6 +109,32 @@ impl StatsMgr {
                         continue;
                     }
 
+                    // alive but no fresh metrics, refresh the last full report
+                    if stat.frame == "heartbeat" {
+                        if let Ok(mut host_stat_map) = stat_dict_1.lock() {
+                            if let Some(pre_stat) = host_stat_map.get_mut(&info.name) {
+                                let now = SystemTime::now()
+                                    .duration_since(UNIX_EPOCH)
+                                    .unwrap()
+                                    .as_secs();
+                                if info.notify && (pre_stat.latest_ts + cfg.offline_threshold < now)
+                                {
+                                    // node up notify
+                                    notifier_tx_1.send((Event::NodeUp, pre_stat.to_owned()));
+                                }
+                                let pre_t = pre_stat.to_mut();
+                                pre_t.latest_ts = now;
+                                pre_t.disabled = info.disabled;
+                                pre_t.online4 = stat.online4;
+                                pre_t.online6 = stat.online6;
+                                pre_t.uptime = stat.uptime;
+                                pre_t.uptime_str = uptime_str(stat.uptime);
+                                pre_t.seq = stat.seq;
+                            }
+                        }
+                        continue;
+                    }
+
                     let local_now = Local::now();
                     // 补齐
                     let mut stat_c = stat;
@@ -126,17 +166,7 @@ impl StatsMgr {
                     }
 
                     // uptime str
-                    let day = (stat_t.uptime as f64 / 3600.0 / 24.0) as i64;
-                    if day > 0 {
-                        stat_t.uptime_str = format!("{} 天", day);
-                    } else {
-                        stat_t.uptime_str = format!(
-                            "{:02}:{:02}:{:02}",
-                            (stat_t.uptime as f64 / 3600.0) as i64,
-                            (stat_t.uptime as f64 / 60.0) as i64 % 60,
-                            stat_t.uptime % 60
-                        );
-                    }
+                    stat_t.uptime_str = uptime_str(stat_t.uptime);
 
                     info!("update stat `{:?}", stat_t);
                     if let Ok(mut host_stat_map) = stat_dict_1.lock() {
@@ -263,8 +293,9 @@ impl StatsMgr {
                 STAT_SENDER.get().unwrap().clone();
         }
 
-        match serde_json::from_value(data) {
-            Ok(stat) => {
+        match serde_json::from_value::<HostStat>(data) {
+            Ok(mut stat) => {
+                stat.normalize_units();
                 trace!("send stat => {:?} ", stat);
                 SENDER.send(Cow::Owned(stat));
             }